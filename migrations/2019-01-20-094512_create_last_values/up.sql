@@ -0,0 +1,8 @@
+CREATE TABLE last_values (
+    node_id                 INTEGER,
+    child_sensor_id         INTEGER,
+    set_type                VARCHAR,
+    value                   VARCHAR,
+    updated_at              TIMESTAMP,
+  PRIMARY KEY(node_id, child_sensor_id, set_type)
+);
@@ -0,0 +1 @@
+DROP TABLE sensor_values;
@@ -0,0 +1,10 @@
+CREATE TABLE sensor_values (
+    id                      INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    node_id                 INTEGER NOT NULL,
+    child_sensor_id         INTEGER NOT NULL,
+    set_type                VARCHAR NOT NULL,
+    value                   VARCHAR NOT NULL,
+    numeric_value           DOUBLE,
+    received_at             TIMESTAMP NOT NULL
+);
+CREATE INDEX sensor_values_by_sensor ON sensor_values (node_id, child_sensor_id, received_at);
@@ -0,0 +1,2 @@
+alter table nodes DROP COLUMN last_seen;
+alter table nodes DROP COLUMN timeout_secs;
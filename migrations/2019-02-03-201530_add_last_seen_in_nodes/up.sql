@@ -0,0 +1,2 @@
+ALTER TABLE nodes ADD COLUMN last_seen TIMESTAMP;
+ALTER TABLE nodes ADD COLUMN timeout_secs INTEGER;
@@ -0,0 +1,2 @@
+DROP TABLE battery_levels;
+alter table nodes DROP COLUMN battery_level;
@@ -0,0 +1,8 @@
+ALTER TABLE nodes ADD COLUMN battery_level INTEGER;
+CREATE TABLE battery_levels (
+    id                      INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    node_id                 INTEGER NOT NULL,
+    level                   INTEGER NOT NULL,
+    reported_at             TIMESTAMP NOT NULL
+);
+CREATE INDEX battery_levels_by_node ON battery_levels (node_id, reported_at);
@@ -0,0 +1,2 @@
+alter table nodes DROP COLUMN sketch_name;
+alter table nodes DROP COLUMN sketch_version;
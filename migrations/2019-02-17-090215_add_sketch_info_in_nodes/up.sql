@@ -0,0 +1,2 @@
+ALTER TABLE nodes ADD COLUMN sketch_name VARCHAR;
+ALTER TABLE nodes ADD COLUMN sketch_version VARCHAR;
@@ -0,0 +1,2 @@
+alter table nodes DROP COLUMN library_version;
+alter table nodes DROP COLUMN is_repeater;
@@ -0,0 +1,3 @@
+ALTER TABLE nodes ADD COLUMN library_version VARCHAR;
+ALTER TABLE nodes ADD COLUMN is_repeater BOOLEAN NOT NULL DEFAULT 0;
+DELETE FROM sensors WHERE sensor_type IN ('arduino_node', 'arduino_repeater_node');
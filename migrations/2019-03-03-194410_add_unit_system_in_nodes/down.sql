@@ -0,0 +1 @@
+alter table nodes DROP COLUMN unit_system;
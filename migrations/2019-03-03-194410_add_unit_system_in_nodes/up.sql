@@ -0,0 +1 @@
+ALTER TABLE nodes ADD COLUMN unit_system VARCHAR;
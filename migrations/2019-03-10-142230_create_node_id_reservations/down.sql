@@ -0,0 +1,2 @@
+DROP TABLE deleted_nodes;
+DROP TABLE node_id_reservations;
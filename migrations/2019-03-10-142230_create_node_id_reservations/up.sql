@@ -0,0 +1,9 @@
+CREATE TABLE node_id_reservations (
+    node_id                 INTEGER PRIMARY KEY NOT NULL,
+    note                    TEXT,
+    reserved_at             TIMESTAMP NOT NULL
+);
+CREATE TABLE deleted_nodes (
+    node_id                 INTEGER PRIMARY KEY NOT NULL,
+    deleted_at              TIMESTAMP NOT NULL
+);
@@ -0,0 +1,9 @@
+CREATE TABLE node_pings (
+    id                      INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    node_id                 INTEGER NOT NULL,
+    sent_at                 TIMESTAMP NOT NULL,
+    received_at             TIMESTAMP,
+    hops                    INTEGER,
+    round_trip_ms           INTEGER
+);
+CREATE INDEX node_pings_by_node ON node_pings (node_id, sent_at);
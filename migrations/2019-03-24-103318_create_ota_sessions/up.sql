@@ -0,0 +1,14 @@
+CREATE TABLE ota_sessions (
+    id                      INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    node_id                 INTEGER NOT NULL,
+    firmware_type           INTEGER NOT NULL,
+    firmware_version        INTEGER NOT NULL,
+    total_blocks            INTEGER NOT NULL,
+    blocks_requested        INTEGER NOT NULL DEFAULT 0,
+    highest_block           INTEGER,
+    lowest_block            INTEGER,
+    started_at              TIMESTAMP NOT NULL,
+    last_block_at           TIMESTAMP,
+    status                  VARCHAR NOT NULL
+);
+CREATE INDEX ota_sessions_by_node ON ota_sessions (node_id, started_at);
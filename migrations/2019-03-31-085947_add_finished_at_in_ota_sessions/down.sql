@@ -0,0 +1 @@
+alter table ota_sessions DROP COLUMN finished_at;
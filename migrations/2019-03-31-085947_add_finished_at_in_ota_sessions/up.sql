@@ -0,0 +1 @@
+ALTER TABLE ota_sessions ADD COLUMN finished_at TIMESTAMP;
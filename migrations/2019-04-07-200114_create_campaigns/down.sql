@@ -0,0 +1,2 @@
+DROP TABLE campaign_nodes;
+DROP TABLE campaigns;
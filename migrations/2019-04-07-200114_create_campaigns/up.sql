@@ -0,0 +1,16 @@
+CREATE TABLE campaigns (
+    id                      INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    firmware_type           INTEGER NOT NULL,
+    firmware_version        INTEGER NOT NULL,
+    soak_minutes            INTEGER NOT NULL,
+    status                  VARCHAR NOT NULL,
+    created_at              TIMESTAMP NOT NULL,
+    promoted_at             TIMESTAMP,
+    finished_at             TIMESTAMP
+);
+CREATE TABLE campaign_nodes (
+    campaign_id             INTEGER NOT NULL,
+    node_id                 INTEGER NOT NULL,
+    canary                  BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY (campaign_id, node_id)
+);
@@ -0,0 +1 @@
+DROP TABLE firmware_history;
@@ -0,0 +1,9 @@
+CREATE TABLE firmware_history (
+    id                      INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    node_id                 INTEGER NOT NULL,
+    firmware_type           INTEGER NOT NULL,
+    firmware_version        INTEGER NOT NULL,
+    observed_at             TIMESTAMP NOT NULL,
+    bad                     BOOLEAN NOT NULL DEFAULT 0
+);
+CREATE INDEX firmware_history_by_node ON firmware_history (node_id, observed_at);
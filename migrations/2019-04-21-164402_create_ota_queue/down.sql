@@ -0,0 +1 @@
+DROP TABLE ota_queue;
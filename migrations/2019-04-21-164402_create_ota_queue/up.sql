@@ -0,0 +1,6 @@
+CREATE TABLE ota_queue (
+    node_id                 INTEGER PRIMARY KEY NOT NULL,
+    firmware_type           INTEGER NOT NULL,
+    firmware_version        INTEGER NOT NULL,
+    queued_at               TIMESTAMP NOT NULL
+);
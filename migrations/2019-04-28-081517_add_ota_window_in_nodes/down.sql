@@ -0,0 +1 @@
+alter table nodes DROP COLUMN ota_window;
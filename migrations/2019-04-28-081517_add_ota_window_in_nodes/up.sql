@@ -0,0 +1 @@
+ALTER TABLE nodes ADD COLUMN ota_window VARCHAR;
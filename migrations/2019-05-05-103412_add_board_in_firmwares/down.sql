@@ -0,0 +1 @@
+alter table firmwares DROP COLUMN board;
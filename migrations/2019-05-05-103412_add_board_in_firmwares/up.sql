@@ -0,0 +1 @@
+ALTER TABLE firmwares ADD COLUMN board VARCHAR NOT NULL DEFAULT 'atmega328';
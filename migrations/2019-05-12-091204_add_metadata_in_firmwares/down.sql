@@ -0,0 +1,5 @@
+alter table firmwares DROP COLUMN git_revision;
+alter table firmwares DROP COLUMN filename;
+alter table firmwares DROP COLUMN sha256;
+alter table firmwares DROP COLUMN uploaded_at;
+alter table firmwares DROP COLUMN description;
@@ -0,0 +1,5 @@
+ALTER TABLE firmwares ADD COLUMN description VARCHAR;
+ALTER TABLE firmwares ADD COLUMN uploaded_at TIMESTAMP;
+ALTER TABLE firmwares ADD COLUMN sha256 VARCHAR;
+ALTER TABLE firmwares ADD COLUMN filename VARCHAR;
+ALTER TABLE firmwares ADD COLUMN git_revision VARCHAR;
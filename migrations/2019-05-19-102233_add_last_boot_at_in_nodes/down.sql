@@ -0,0 +1 @@
+alter table nodes DROP COLUMN last_boot_at;
@@ -0,0 +1 @@
+ALTER TABLE nodes ADD COLUMN last_boot_at TIMESTAMP;
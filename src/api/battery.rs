@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Query};
+use chrono::Utc;
+use futures::future::Future;
+
+use crate::api::index::{invalid_request, AppState};
+use crate::handler::battery::*;
+use crate::handler::node::NodeDto;
+
+const DEFAULT_LOW_BATTERY_THRESHOLD: i32 = 20;
+
+pub fn list_low(
+    (req, query): (HttpRequest<AppState>, Query<HashMap<String, String>>),
+) -> FutureResponse<HttpResponse> {
+    let threshold = match query.get("threshold") {
+        Some(threshold) => match threshold.parse::<i32>() {
+            Ok(value) => value,
+            Err(_) => return invalid_request("threshold should be a battery percentage"),
+        },
+        None => DEFAULT_LOW_BATTERY_THRESHOLD,
+    };
+    let node_timeout_secs = req.state().node_timeout_secs;
+    req.state()
+        .db
+        .send(ListLowBatteryNodes { threshold })
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(nodes) => {
+                let now = Utc::now().naive_utc();
+                let nodes: Vec<NodeDto> = nodes
+                    .into_iter()
+                    .map(|node| NodeDto::new(node, node_timeout_secs, now))
+                    .collect();
+                Ok(HttpResponse::Ok().json(nodes))
+            }
+            Err(e) => {
+                error!("Error while getting low battery nodes {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+pub fn node_history(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let node_id = match req.match_info().get("node_id").map(|id| id.parse::<i32>()) {
+        Some(Ok(value)) => value,
+        _ => return invalid_request("node_id should be a number"),
+    };
+    req.state()
+        .db
+        .send(GetBatteryLevels { node_id })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(levels) => Ok(HttpResponse::Ok().json(levels)),
+            Err(e) => {
+                error!("Error while getting battery levels {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
@@ -0,0 +1,92 @@
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Json};
+use futures::future::Future;
+use http::StatusCode;
+
+use crate::api::index::{invalid_request, AppState};
+use crate::handler::campaign::*;
+
+pub fn list(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    req.state()
+        .db
+        .send(ListCampaigns)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(campaigns) => Ok(HttpResponse::Ok().json(campaigns)),
+            Err(e) => {
+                error!("Error while getting campaigns {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+pub fn create(
+    (req, campaign): (HttpRequest<AppState>, Json<NewCampaignRequest>),
+) -> FutureResponse<HttpResponse> {
+    req.state()
+        .db
+        .send(campaign.into_inner())
+        .from_err()
+        .and_then(|res| match res {
+            Ok(campaign) => Ok(HttpResponse::Ok().json(campaign)),
+            Err(msg) => Ok(HttpResponse::build(StatusCode::from_u16(msg.status).unwrap()).json(msg)),
+        })
+        .responder()
+}
+
+pub fn get_campaign(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let campaign_id = match campaign_id(req) {
+        Some(campaign_id) => campaign_id,
+        None => return invalid_request("campaign_id should be a number"),
+    };
+    req.state()
+        .db
+        .send(GetCampaign { campaign_id })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(Some(campaign)) => Ok(HttpResponse::Ok().json(campaign)),
+            Ok(None) => Ok(HttpResponse::NotFound().into()),
+            Err(e) => {
+                error!("Error while getting campaign {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+pub fn pause(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    change_campaign(req, CampaignAction::Pause)
+}
+
+pub fn resume(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    change_campaign(req, CampaignAction::Resume)
+}
+
+pub fn abort(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    change_campaign(req, CampaignAction::Abort)
+}
+
+fn change_campaign(req: &HttpRequest<AppState>, action: CampaignAction) -> FutureResponse<HttpResponse> {
+    let campaign_id = match campaign_id(req) {
+        Some(campaign_id) => campaign_id,
+        None => return invalid_request("campaign_id should be a number"),
+    };
+    req.state()
+        .db
+        .send(ChangeCampaign { campaign_id, action })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(HttpResponse::build(StatusCode::from_u16(msg.status).unwrap()).json(msg)),
+            Err(e) => {
+                error!("Error while changing campaign {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+fn campaign_id(req: &HttpRequest<AppState>) -> Option<i32> {
+    req.match_info()
+        .get("campaign_id")
+        .and_then(|id| id.parse::<i32>().ok())
+}
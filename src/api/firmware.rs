@@ -1,7 +1,11 @@
 use std::collections::HashMap;
+use std::env;
 use std::fs;
+use std::io;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use actix_web::{
     AsyncResponder, dev, error, Error, FutureResponse, HttpMessage, HttpRequest, HttpResponse,
@@ -11,10 +15,47 @@ use futures::{Future, Stream};
 use futures::future;
 use http::StatusCode;
 
-use crate::api::index::AppState;
+use crate::api::index::{invalid_request, AppState};
 use crate::handler::firmware::*;
 use crate::handler::response::Msgs;
-use crate::model::firmware::Firmware;
+use crate::model::firmware::{Board, Firmware, FirmwareImage};
+
+static UPLOAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+const MAX_FIELD_SIZE: usize = 4096;
+/// Intel HEX files take about three times the size of the flash image they hold.
+const FILE_SIZE_FACTOR: usize = 3;
+
+/// Part of a multipart upload, files are streamed to temp files.
+enum UploadPart {
+    Field(String, String),
+    File {
+        temp_file: TempFile,
+        filename: Option<String>,
+    },
+}
+
+/// Uploaded file in the temp directory, removed once dropped whether the upload
+/// succeeded or failed halfway.
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        match fs::remove_file(&self.0) {
+            Ok(_) => info!("Cleared temp firmware file"),
+            Err(e) => info!("Error in clearing temp firmware file {:?}", e),
+        };
+    }
+}
+
+/// Everything about an uploaded firmware besides its file.
+struct UploadFields {
+    firmware_type: u8,
+    firmware_version: u8,
+    firmware_name: String,
+    board: Board,
+    description: Option<String>,
+    git_revision: Option<String>,
+}
 
 pub fn upload_form(_req: &HttpRequest<AppState>) -> Result<HttpResponse, error::Error> {
     let html = r#"<html>
@@ -24,6 +65,13 @@ pub fn upload_form(_req: &HttpRequest<AppState>) -> Result<HttpResponse, error::
                 Name: <input type="text" name="firmware_name"/><br>
                 Type: <input type="text" name="firmware_type"/><br>
                 Version: <input type="text" name="firmware_version"/><br>
+                Description: <input type="text" name="description"/><br>
+                Git revision: <input type="text" name="git_revision"/><br>
+                Board: <select name="board">
+                    <option value="atmega328">ATmega328</option>
+                    <option value="atmega1284p">ATmega1284P</option>
+                    <option value="atmega2560">ATmega2560</option>
+                </select><br>
                 <input type="file" name="firmware_file"/><br>
                 <input type="submit" value="Submit"/>
             </form>
@@ -33,10 +81,24 @@ pub fn upload_form(_req: &HttpRequest<AppState>) -> Result<HttpResponse, error::
     Ok(HttpResponse::Ok().body(html))
 }
 
-pub fn list(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+pub fn list(
+    (req, query): (HttpRequest<AppState>, Query<HashMap<String, String>>),
+) -> FutureResponse<HttpResponse> {
+    let firmware_type = match query.get("firmware_type").map(|firmware_type| firmware_type.parse::<i32>()) {
+        Some(Ok(firmware_type)) => Some(firmware_type),
+        Some(Err(_)) => return invalid_request("firmware_type should be a number with max value of 255"),
+        None => None,
+    };
+    let board = match query.get("board") {
+        Some(board) => match parse_board(Some(board)) {
+            Ok(board) => Some(board),
+            Err(msg) => return invalid_request(msg),
+        },
+        None => None,
+    };
     req.state()
         .db
-        .send(ListFirmwares)
+        .send(ListFirmwares { firmware_type, board })
         .from_err()
         .and_then(|res| match res {
             Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
@@ -45,30 +107,117 @@ pub fn list(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
         .responder()
 }
 
-pub fn delete(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
-    let firmware_type = match req.match_info().get("firmware_type") {
-        Some(firmware_type) => match firmware_type.parse::<i32>() {
-            Ok(value) => value,
-            Err(_) => {
-                return invalid_request("firmware_type should be a number with max value of 255");
+pub fn get(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let (firmware_type, firmware_version) = match firmware_key(req) {
+        Ok(key) => key,
+        Err(msg) => return invalid_request(msg),
+    };
+    req.state()
+        .db
+        .send(GetFirmware {
+            firmware_type,
+            firmware_version,
+        })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(firmware) => Ok(HttpResponse::Ok().json(FirmwareDto::new(&firmware))),
+            Err(_) => Ok(HttpResponse::NotFound().json("firmware is not present")),
+        })
+        .responder()
+}
+
+pub fn download(
+    (req, query): (HttpRequest<AppState>, Query<HashMap<String, String>>),
+) -> FutureResponse<HttpResponse> {
+    let (firmware_type, firmware_version) = match firmware_key(&req) {
+        Ok(key) => key,
+        Err(msg) => return invalid_request(msg),
+    };
+    let binary = match query.get("format").map(String::as_str) {
+        Some("hex") | None => false,
+        Some("bin") => true,
+        Some(_) => return invalid_request("format should be either hex or bin"),
+    };
+    req.state()
+        .db
+        .send(GetFirmware {
+            firmware_type,
+            firmware_version,
+        })
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(firmware) => {
+                let file_name = download_file_name(&firmware);
+                if binary {
+                    return Ok(HttpResponse::Ok()
+                        .content_type("application/octet-stream")
+                        .header("Content-Disposition", format!("attachment; filename=\"{}.bin\"", file_name))
+                        .body(firmware.data));
+                }
+                match firmware.to_ihex() {
+                    Ok(ihex) => Ok(HttpResponse::Ok()
+                        .content_type("text/plain")
+                        .header("Content-Disposition", format!("attachment; filename=\"{}.hex\"", file_name))
+                        .body(ihex)),
+                    Err(e) => {
+                        error!("Error while converting firmware to intel hex {:?}", e);
+                        Ok(HttpResponse::InternalServerError().into())
+                    }
+                }
             }
-        },
-        None => return invalid_request("firmware_type path param is missing"),
+            Err(_) => Ok(HttpResponse::NotFound().json("firmware is not present")),
+        })
+        .responder()
+}
+
+/// Keeps the file name a valid header value whatever the firmware name holds.
+fn download_file_name(firmware: &Firmware) -> String {
+    let name: String = firmware
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    format!("{}__{}__{}", firmware.firmware_type, firmware.firmware_version, name)
+}
+
+pub fn nodes(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let (firmware_type, firmware_version) = match firmware_key(req) {
+        Ok(key) => key,
+        Err(msg) => return invalid_request(msg),
     };
-    let firmware_version = match req.match_info().get("firmware_version") {
-        Some(firmware_type) => match firmware_type.parse::<i32>() {
-            Ok(value) => value,
-            Err(_) => {
-                return invalid_request("firmware_version should be a number with max value of 255");
+    req.state()
+        .db
+        .send(GetFirmwareNodes {
+            firmware_type,
+            firmware_version,
+        })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(firmware_nodes) => Ok(HttpResponse::Ok().json(firmware_nodes)),
+            Err(e) => {
+                error!("Error while getting nodes of firmware {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
             }
-        },
-        None => return invalid_request("firmware_version path param is missing"),
+        })
+        .responder()
+}
+
+pub fn delete(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let (firmware_type, firmware_version) = match firmware_key(req) {
+        Ok(key) => key,
+        Err(msg) => return invalid_request(msg),
+    };
+    let force = match req.query().get("force").map(String::as_str) {
+        Some("true") => true,
+        Some("false") | None => false,
+        Some(_) => return invalid_request("force should be either true or false"),
     };
     req.state()
         .db
         .send(DeleteFirmware {
             firmware_type,
             firmware_version,
+            force,
         })
         .from_err()
         .and_then(|res| match res {
@@ -101,6 +250,10 @@ pub fn create_or_update(
         Some(firmware_name) => firmware_name.to_owned(),
         None => return invalid_request("firmware name is not present"),
     };
+    let board = match parse_board(query.get("board")) {
+        Ok(board) => board,
+        Err(msg) => return invalid_request(msg),
+    };
     let firmware_type = match req.match_info().get("firmware_type") {
         Some(firmware_type) => match firmware_type.parse::<u8>() {
             Ok(value) => value,
@@ -119,6 +272,14 @@ pub fn create_or_update(
         },
         None => return invalid_request("firmware_version path param is missing"),
     };
+    let fields = UploadFields {
+        firmware_type,
+        firmware_version,
+        firmware_name,
+        board,
+        description: query.get("description").cloned(),
+        git_revision: query.get("git_revision").cloned(),
+    };
     let req_clone = req.clone();
     Box::new(
         req_clone
@@ -127,90 +288,199 @@ pub fn create_or_update(
             .map(handle_multipart_item)
             .flatten()
             .collect()
-            .and_then(move |file_paths| match file_paths.get(0) {
-                Some(file_path) => {
-                    let firmware = get_firmware(
-                        file_path.to_owned(),
-                        firmware_type,
-                        firmware_version,
-                        firmware_name,
-                    );
-                    match fs::remove_file(file_path) {
-                        Ok(_) => info!("Cleared temp firmware file"),
-                        Err(e) => info!("Error in clearing temp firmware file {:?}", e),
-                    };
-                    match firmware {
-                        Ok(firmware) => req
-                            .state()
-                            .db
-                            .send(if update {
-                                CreateOrUpdate::Update(firmware)
-                            } else {
-                                CreateOrUpdate::Create(firmware)
-                            })
-                            .from_err()
-                            .and_then(|res| match res {
-                                Ok(msg) => Ok(HttpResponse::build(
-                                    StatusCode::from_u16(msg.status).unwrap(),
-                                )
-                                    .json(msg)),
-                                Err(e) => {
-                                    Ok(HttpResponse::build(StatusCode::from_u16(e.status).unwrap())
-                                        .json(e))
-                                }
-                            })
-                            .responder(),
-
-                        Err(msg) => Box::new(future::result(Ok(HttpResponse::build(
-                            StatusCode::from_u16(msg.status).unwrap(),
-                        )
-                            .json(msg)))),
-                    }
-                }
-                None => Box::new(future::result(Ok(
-                    HttpResponse::InternalServerError().into()
-                ))),
-            }),
+            .and_then(move |parts| store_firmware(&req, &parts, fields, update)),
     )
 }
 
-fn invalid_request(msg: &str) -> FutureResponse<HttpResponse> {
-    Box::new(future::result(Ok(HttpResponse::build(
-        StatusCode::from_u16(400).unwrap(),
+/// Creates a firmware from a multipart form with firmware_type, firmware_version,
+/// firmware_name, board, description, git_revision and firmware_file fields.
+pub fn upload(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let req_clone = req.clone();
+    Box::new(
+        req_clone
+            .multipart()
+            .map_err(error::ErrorInternalServerError)
+            .map(handle_multipart_item)
+            .flatten()
+            .collect()
+            .and_then(move |parts| {
+                let fields: HashMap<&str, &str> = parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        UploadPart::Field(name, value) => Some((name.as_str(), value.trim())),
+                        UploadPart::File { .. } => None,
+                    })
+                    .collect();
+                let upload_fields = upload_fields(&fields);
+                match upload_fields {
+                    Ok(upload_fields) => store_firmware(&req, &parts, upload_fields, false),
+                    Err(msg) => invalid_request(msg),
+                }
+            }),
     )
-        .json(msg))))
 }
 
-fn get_firmware(
-    file_name: String,
-    firmware_type: u8,
-    firmware_version: u8,
-    firmware_name: String,
-) -> Result<NewFirmware, Msgs> {
-    match Firmware::prepare_fw(
-        i32::from(firmware_type),
-        i32::from(firmware_version),
+fn upload_fields(fields: &HashMap<&str, &str>) -> Result<UploadFields, &'static str> {
+    let firmware_type = fields
+        .get("firmware_type")
+        .ok_or("firmware_type is not present")?
+        .parse::<u8>()
+        .map_err(|_| "firmware_type should be a number with max value of 255")?;
+    let firmware_version = fields
+        .get("firmware_version")
+        .ok_or("firmware_version is not present")?
+        .parse::<u8>()
+        .map_err(|_| "firmware_version should be a number with max value of 255")?;
+    let firmware_name = match fields.get("firmware_name") {
+        Some(firmware_name) if !firmware_name.is_empty() => firmware_name.to_string(),
+        _ => return Err("firmware name is not present"),
+    };
+    let board = parse_board(fields.get("board").filter(|board| !board.is_empty()))?;
+    let optional_field = |name| {
+        fields
+            .get(name)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    };
+    Ok(UploadFields {
+        firmware_type,
+        firmware_version,
         firmware_name,
-        &PathBuf::from(file_name),
-    ) {
-        Some(firmware) => Ok(NewFirmware::build(
-            firmware.firmware_type,
-            firmware.firmware_version,
-            firmware.name,
-            firmware.data,
-        )),
-        _ => Err(Msgs {
+        board,
+        description: optional_field("description"),
+        git_revision: optional_field("git_revision"),
+    })
+}
+
+fn parse_board<T: AsRef<str>>(board: Option<T>) -> Result<Board, &'static str> {
+    match board {
+        Some(board) => serde_json::from_value(json!(board.as_ref()))
+            .map_err(|_| "board should be one of atmega328, atmega1284p or atmega2560"),
+        None => Ok(Board::Atmega328),
+    }
+}
+
+fn store_firmware(
+    req: &HttpRequest<AppState>,
+    parts: &[UploadPart],
+    fields: UploadFields,
+    update: bool,
+) -> FutureResponse<HttpResponse> {
+    let file = parts.iter().find_map(|part| match part {
+        UploadPart::File { temp_file, filename } => Some((&temp_file.0, filename.clone())),
+        UploadPart::Field(_, _) => None,
+    });
+    let firmware = match file {
+        Some((file_path, filename)) => get_firmware(file_path, filename, fields),
+        None => Err(Msgs {
             status: 400,
             message: "Error uploading firmware, Missing file".to_string(),
         }),
+    };
+    match firmware {
+        Ok(firmware) => req
+            .state()
+            .db
+            .send(if update {
+                CreateOrUpdate::Update(firmware)
+            } else {
+                CreateOrUpdate::Create(firmware)
+            })
+            .from_err()
+            .and_then(|res| match res {
+                Ok(msg) => Ok(HttpResponse::build(
+                    StatusCode::from_u16(msg.status).unwrap(),
+                )
+                    .json(msg)),
+                Err(e) => {
+                    Ok(HttpResponse::build(StatusCode::from_u16(e.status).unwrap())
+                        .json(e))
+                }
+            })
+            .responder(),
+
+        Err(msg) => Box::new(future::result(Ok(HttpResponse::build(
+            StatusCode::from_u16(msg.status).unwrap(),
+        )
+            .json(msg)))),
     }
 }
 
+fn firmware_key(req: &HttpRequest<AppState>) -> Result<(i32, i32), &'static str> {
+    let firmware_type = match req.match_info().get("firmware_type") {
+        Some(firmware_type) => firmware_type
+            .parse::<i32>()
+            .map_err(|_| "firmware_type should be a number with max value of 255")?,
+        None => return Err("firmware_type path param is missing"),
+    };
+    let firmware_version = match req.match_info().get("firmware_version") {
+        Some(firmware_version) => firmware_version
+            .parse::<i32>()
+            .map_err(|_| "firmware_version should be a number with max value of 255")?,
+        None => return Err("firmware_version path param is missing"),
+    };
+    Ok((firmware_type, firmware_version))
+}
+
+fn get_firmware(
+    file_path: &Path,
+    filename: Option<String>,
+    fields: UploadFields,
+) -> Result<NewFirmware, Msgs> {
+    let UploadFields {
+        firmware_type,
+        firmware_version,
+        firmware_name,
+        board,
+        description,
+        git_revision,
+    } = fields;
+    Firmware::prepare_fw(board, file_path)
+        .and_then(|FirmwareImage { data, sha256 }| {
+            Firmware::build(
+                i32::from(firmware_type),
+                i32::from(firmware_version),
+                firmware_name,
+                board,
+                data,
+            ).map(|firmware| NewFirmware {
+                firmware_type: firmware.firmware_type,
+                firmware_version: firmware.firmware_version,
+                name: firmware.name,
+                blocks: firmware.blocks,
+                crc: firmware.crc,
+                data: firmware.data,
+                board: firmware.board,
+                description,
+                sha256: Some(sha256),
+                filename,
+                git_revision,
+            })
+        })
+        .map_err(|e| Msgs {
+            status: 400,
+            message: format!("Error uploading firmware, {}", e),
+        })
+}
+
 fn handle_multipart_item(
     item: actix_web::multipart::MultipartItem<dev::Payload>,
-) -> Box<dyn Stream<Item=String, Error=Error>> {
+) -> Box<dyn Stream<Item=UploadPart, Error=Error>> {
     match item {
-        multipart::MultipartItem::Field(field) => Box::new(save_file(field).into_stream()),
+        multipart::MultipartItem::Field(field) => {
+            let filename = field
+                .content_disposition()
+                .and_then(|disposition| disposition.get_filename().map(str::to_owned));
+            if filename.is_some() {
+                Box::new(
+                    save_file(field)
+                        .map(|temp_file| UploadPart::File { temp_file, filename })
+                        .into_stream(),
+                )
+            } else {
+                Box::new(read_field(field).into_stream())
+            }
+        }
         multipart::MultipartItem::Nested(mp) => Box::new(
             mp.map_err(error::ErrorInternalServerError)
                 .map(handle_multipart_item)
@@ -219,22 +489,63 @@ fn handle_multipart_item(
     }
 }
 
-pub fn save_file(
+fn read_field(
     field: actix_web::multipart::Field<dev::Payload>,
-) -> Box<dyn Future<Item=String, Error=Error>> {
-    //TODO: create unique temp files for each upload to handle concurrent uploads
-    let file_path_string = "firmware.hex";
-    let file_path: String = file_path_string.to_owned();
-    let mut file = match fs::File::create(file_path_string) {
-        Ok(file) => file,
+) -> Box<dyn Future<Item=UploadPart, Error=Error>> {
+    let name = field
+        .content_disposition()
+        .and_then(|disposition| disposition.get_name().map(str::to_owned))
+        .unwrap_or_default();
+    Box::new(
+        field
+            .fold(Vec::new(), |mut value, bytes| {
+                if value.len() + bytes.len() > MAX_FIELD_SIZE {
+                    return future::result(Err(error::MultipartError::Payload(error::PayloadError::Overflow)));
+                }
+                value.extend_from_slice(bytes.as_ref());
+                future::result(Ok(value))
+            })
+            .map_err(|e| match e {
+                error::MultipartError::Payload(error::PayloadError::Overflow) => error::PayloadError::Overflow.into(),
+                e => error::ErrorInternalServerError(e),
+            })
+            .map(move |value| UploadPart::Field(name, String::from_utf8_lossy(&value).into_owned())),
+    )
+}
+
+/// Unique file in the temp directory, so concurrent uploads don't overwrite each other.
+fn create_temp_file() -> io::Result<(TempFile, fs::File)> {
+    let file_path = env::temp_dir().join(format!(
+        "myscontroller-firmware-{}-{}",
+        process::id(),
+        UPLOAD_COUNT.fetch_add(1, Ordering::SeqCst)
+    ));
+    let file = fs::OpenOptions::new().write(true).create_new(true).open(&file_path)?;
+    Ok((TempFile(file_path), file))
+}
+
+/// Largest firmware file accepted, enough for a HEX file filling the largest flash.
+fn max_file_size() -> usize {
+    Board::ALL.iter().map(|board| board.flash_size()).max().unwrap_or(0) * FILE_SIZE_FACTOR
+}
+
+fn save_file(
+    field: actix_web::multipart::Field<dev::Payload>,
+) -> Box<dyn Future<Item=TempFile, Error=Error>> {
+    let (temp_file, mut file) = match create_temp_file() {
+        Ok(temp_file) => temp_file,
         Err(e) => return Box::new(future::err(error::ErrorInternalServerError(e))),
     };
+    let max_size = max_file_size();
     Box::new(
         field
-            .fold(0i64, move |acc, bytes| {
+            .fold(0usize, move |acc, bytes| {
+                if acc + bytes.len() > max_size {
+                    return future::result(Err(error::MultipartError::Payload(error::PayloadError::Overflow)));
+                }
                 let rt = file
                     .write_all(bytes.as_ref())
-                    .map(|_| acc + bytes.len() as i64)
+                    .map(|_| acc + bytes.len())
                     .map_err(|e| {
                         error!("file.write_all failed: {:?}", e);
                         error::MultipartError::Payload(error::PayloadError::Io(e))
@@ -243,11 +554,14 @@ pub fn save_file(
             })
             .and_then(|size| {
                 info!("file size {}", size);
-                future::result(Ok(file_path))
+                future::result(Ok(temp_file))
             })
             .map_err(|e| {
                 error!("save_file failed, {:?}", e);
-                error::ErrorInternalServerError(e)
+                match e {
+                    error::MultipartError::Payload(error::PayloadError::Overflow) => error::PayloadError::Overflow.into(),
+                    e => error::ErrorInternalServerError(e),
+                }
             }),
     )
 }
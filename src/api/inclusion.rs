@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use actix_web::{HttpRequest, HttpResponse, Json};
+
+use crate::api::index::AppState;
+use crate::core::inclusion::{DEFAULT_INCLUSION_DURATION, MAX_INCLUSION_DURATION};
+
+#[derive(Deserialize, Debug)]
+pub struct InclusionRequest {
+    pub duration_secs: Option<u64>,
+}
+
+pub fn get_state(req: &HttpRequest<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(req.state().inclusion.state())
+}
+
+pub fn start(
+    (req, inclusion_request): (HttpRequest<AppState>, Json<InclusionRequest>),
+) -> HttpResponse {
+    let duration = inclusion_request
+        .duration_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_INCLUSION_DURATION);
+    if duration.as_secs() == 0 || duration > MAX_INCLUSION_DURATION {
+        return HttpResponse::BadRequest().json(format!(
+            "duration_secs should be between 1 and {}",
+            MAX_INCLUSION_DURATION.as_secs()
+        ));
+    }
+    let inclusion = &req.state().inclusion;
+    match inclusion.start(duration, &req.state().reset_sender) {
+        Ok(_) => {
+            info!("Started inclusion mode for {} seconds", duration.as_secs());
+            HttpResponse::Ok().json(inclusion.state())
+        }
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+pub fn stop(req: &HttpRequest<AppState>) -> HttpResponse {
+    let inclusion = &req.state().inclusion;
+    inclusion.stop(&req.state().reset_sender);
+    info!("Stopped inclusion mode");
+    HttpResponse::Ok().json(inclusion.state())
+}
@@ -1,11 +1,25 @@
 use actix::*;
-use actix_web::{HttpRequest, Result};
+use actix_web::{FutureResponse, HttpRequest, HttpResponse, Result};
+use futures::future;
+use http::StatusCode;
 use crate::channel;
+use crate::core::inclusion::Inclusion;
+use crate::core::node_id_policy::NodeIdPolicy;
 use crate::model::db::ConnDsl;
 
 pub struct AppState {
     pub db: Addr<ConnDsl>,
     pub reset_sender: channel::Sender<String>,
+    pub node_timeout_secs: i64,
+    pub inclusion: Inclusion,
+    pub node_id_policy: NodeIdPolicy,
+}
+
+pub fn invalid_request(msg: &str) -> FutureResponse<HttpResponse> {
+    Box::new(future::result(Ok(HttpResponse::build(
+        StatusCode::from_u16(400).unwrap(),
+    )
+        .json(msg))))
 }
 
 pub fn home(_req: &HttpRequest<AppState>) -> Result<&'static str> {
@@ -15,5 +29,38 @@ pub fn home(_req: &HttpRequest<AppState>) -> Result<&'static str> {
         POST /nodes <node json payload> \n \
         PUT /nodes <node json payload> \n \
         DELETE /nodes <node json payload> \n \
-        POST /reboot_node/<node_id>")
+        POST /nodes/<node_id>/reboot \n \
+        POST /nodes/<node_id>/heartbeat \n \
+        GET /nodes/<node_id>/battery \n \
+        POST /nodes/<node_id>/ping \n \
+        GET /nodes/<node_id>/pings \n \
+        GET /nodes/<node_id>/ota \n \
+        GET /nodes/<node_id>/firmwares \n \
+        POST /nodes/<node_id>/rollback \n \
+        GET /ota/sessions?status=<in_progress|transferred|completed|failed> \n \
+        GET /ota/queue \n \
+        GET /campaigns \n \
+        GET /campaigns/<campaign_id> \n \
+        POST /campaigns <campaign json payload> \n \
+        POST /campaigns/<campaign_id>/pause \n \
+        POST /campaigns/<campaign_id>/resume \n \
+        POST /campaigns/<campaign_id>/abort \n \
+        GET /batteries/low?threshold=<percentage> \n \
+        GET /inclusion \n \
+        POST /inclusion {\"duration_secs\": <seconds>} \n \
+        DELETE /inclusion \n \
+        GET /reservations \n \
+        POST /reservations {\"node_id\": <node_id>, \"note\": <note>} \n \
+        DELETE /reservations/<node_id> \n \
+        GET /topology \n \
+        GET /topology/dot \n \
+        GET /topology/graph \n \
+        POST /topology/discover \n \
+        GET /firmwares?firmware_type=<firmware_type>&board=<atmega328|atmega1284p|atmega2560> \n \
+        GET /firmwares/upload \n \
+        POST /firmwares <multipart form with firmware_type, firmware_version, firmware_name, board, description, git_revision and firmware_file> \n \
+        GET /firmwares/<firmware_type>/<firmware_version> \n \
+        DELETE /firmwares/<firmware_type>/<firmware_version>?force=<true|false> \n \
+        GET /firmwares/<firmware_type>/<firmware_version>/nodes \n \
+        GET /firmwares/<firmware_type>/<firmware_version>/download?format=<hex|bin>")
 }
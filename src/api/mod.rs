@@ -1,4 +1,11 @@
+pub mod battery;
+pub mod campaign;
 pub mod firmware;
+pub mod inclusion;
 pub mod index;
 pub mod node;
+pub mod ota;
+pub mod ping;
+pub mod reservation;
 pub mod sensor;
+pub mod topology;
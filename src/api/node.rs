@@ -1,16 +1,27 @@
 use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Json};
-use crate::api::index::AppState;
+use chrono::Utc;
+use crate::api::index::{invalid_request, AppState};
+use crate::core::maintenance_window::parse_windows;
 use crate::handler::node::*;
+use crate::model::node::MAX_TIMEOUT_SECS;
 use futures::future::Future;
 use http::StatusCode;
 
 pub fn list(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let node_timeout_secs = req.state().node_timeout_secs;
     req.state()
         .db
         .send(ListNodes)
         .from_err()
-        .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+        .and_then(move |res| match res {
+            Ok(nodes) => {
+                let now = Utc::now().naive_utc();
+                let nodes: Vec<NodeDto> = nodes
+                    .into_iter()
+                    .map(|node| NodeDto::new(node, node_timeout_secs, now))
+                    .collect();
+                Ok(HttpResponse::Ok().json(nodes))
+            }
             Err(e) => {
                 error!("Error while getting nodes list {:?}", e);
                 Ok(HttpResponse::InternalServerError().into())
@@ -22,6 +33,12 @@ pub fn list(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
 pub fn create(
     (req, node_update): (HttpRequest<AppState>, Json<NewNode>),
 ) -> FutureResponse<HttpResponse> {
+    if let Err(e) = validate_ota_window(&node_update.ota_window) {
+        return invalid_request(&e);
+    }
+    if let Err(e) = validate_timeout(node_update.timeout_secs) {
+        return invalid_request(&e);
+    }
     req.state()
         .db
         .send(NewNode {
@@ -31,6 +48,9 @@ pub fn create(
             firmware_version: node_update.firmware_version,
             auto_update: node_update.auto_update,
             scheduled: node_update.scheduled,
+            timeout_secs: node_update.timeout_secs,
+            unit_system: node_update.unit_system,
+            ota_window: node_update.ota_window.clone(),
         })
         .from_err()
         .and_then(|res| match res {
@@ -46,6 +66,12 @@ pub fn create(
 pub fn update(
     (req, node_update): (HttpRequest<AppState>, Json<NodeUpdate>),
 ) -> FutureResponse<HttpResponse> {
+    if let Err(e) = validate_ota_window(&node_update.ota_window) {
+        return invalid_request(&e);
+    }
+    if let Err(e) = validate_timeout(node_update.timeout_secs) {
+        return invalid_request(&e);
+    }
     req.state()
         .db
         .send(NodeUpdate {
@@ -55,6 +81,9 @@ pub fn update(
             firmware_version: node_update.firmware_version,
             auto_update: node_update.auto_update,
             scheduled: node_update.scheduled,
+            timeout_secs: node_update.timeout_secs,
+            unit_system: node_update.unit_system,
+            ota_window: node_update.ota_window.clone(),
         })
         .from_err()
         .and_then(|res| match res {
@@ -89,12 +118,17 @@ pub fn delete(
 pub fn get_node(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
     let node_id_path_param = req.match_info().get("node_id").unwrap();
     let node_id = node_id_path_param.to_string().parse::<i32>().unwrap();
+    let node_timeout_secs = req.state().node_timeout_secs;
     req.state()
         .db
         .send(GetNode { node_id })
         .from_err()
-        .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+        .and_then(move |res| match res {
+            Ok(node) => Ok(HttpResponse::Ok().json(NodeDto::new(
+                node,
+                node_timeout_secs,
+                Utc::now().naive_utc(),
+            ))),
             Err(e) => {
                 error!("Error while getting node {:?}", e);
                 Ok(
@@ -131,3 +165,88 @@ pub fn reboot_node(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse>
         })
         .responder()
 }
+
+pub fn heartbeat_node(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let node_id = match req.match_info().get("node_id").map(|id| id.parse::<i32>()) {
+        Some(Ok(value)) => value,
+        _ => return invalid_request("node_id should be a number"),
+    };
+    let reset_sender = req.state().reset_sender.clone();
+    req.state()
+        .db
+        .send(GetNode { node_id })
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(_node) => {
+                reset_sender
+                    .send(format!("{};255;3;0;18;\n", node_id))
+                    .unwrap();
+                Ok(HttpResponse::Ok().body("Sent heartbeat request to node"))
+            }
+            Err(e) => {
+                error!("Error while requesting heartbeat from node {:?}", e);
+                Ok(
+                    HttpResponse::build(StatusCode::from_u16(400).unwrap())
+                        .body("Node not present"),
+                )
+            }
+        })
+        .responder()
+}
+
+pub fn rollback_node(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let node_id = match req.match_info().get("node_id").map(|id| id.parse::<i32>()) {
+        Some(Ok(value)) => value,
+        _ => return invalid_request("node_id should be a number"),
+    };
+    req.state()
+        .db
+        .send(RollbackNode { node_id })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(HttpResponse::build(StatusCode::from_u16(msg.status).unwrap()).json(msg)),
+            Err(e) => {
+                error!("Error while rolling back node {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+pub fn firmware_history(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let node_id = match req.match_info().get("node_id").map(|id| id.parse::<i32>()) {
+        Some(Ok(value)) => value,
+        _ => return invalid_request("node_id should be a number"),
+    };
+    req.state()
+        .db
+        .send(GetFirmwareHistory { node_id })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(history) => Ok(HttpResponse::Ok().json(history)),
+            Err(e) => {
+                error!("Error while getting firmware history {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+fn validate_timeout(timeout_secs: Option<i32>) -> Result<(), String> {
+    match timeout_secs {
+        Some(timeout_secs) if timeout_secs < 1 || i64::from(timeout_secs) > MAX_TIMEOUT_SECS => Err(format!(
+            "timeout_secs should be a number of seconds from 1 to {}",
+            MAX_TIMEOUT_SECS
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_ota_window(ota_window: &Option<String>) -> Result<(), String> {
+    match ota_window {
+        Some(ota_window) => parse_windows(ota_window)
+            .map(|_| ())
+            .map_err(|e| format!("ota_window is invalid, {}", e)),
+        None => Ok(()),
+    }
+}
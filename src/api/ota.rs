@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Query};
+use futures::future::Future;
+
+use crate::api::index::{invalid_request, AppState};
+use crate::handler::ota::*;
+
+pub fn node_sessions(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let node_id = match req.match_info().get("node_id").map(|id| id.parse::<i32>()) {
+        Some(Ok(value)) => value,
+        _ => return invalid_request("node_id should be a number"),
+    };
+    get_sessions(req, GetOtaSessions {
+        node_id: Some(node_id),
+        status: None,
+    })
+}
+
+pub fn list_sessions(
+    (req, query): (HttpRequest<AppState>, Query<HashMap<String, String>>),
+) -> FutureResponse<HttpResponse> {
+    let status = match query.get("status") {
+        Some(status) => match serde_json::from_value(json!(status)) {
+            Ok(status) => Some(status),
+            Err(_) => {
+                return invalid_request("status should be one of in_progress, transferred, completed or failed")
+            }
+        },
+        None => None,
+    };
+    get_sessions(&req, GetOtaSessions {
+        node_id: None,
+        status,
+    })
+}
+
+pub fn queue(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    req.state()
+        .db
+        .send(GetOtaQueue)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(queue) => Ok(HttpResponse::Ok().json(queue)),
+            Err(e) => {
+                error!("Error while getting ota queue {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+fn get_sessions(
+    req: &HttpRequest<AppState>,
+    request: GetOtaSessions,
+) -> FutureResponse<HttpResponse> {
+    req.state()
+        .db
+        .send(request)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(sessions) => {
+                let sessions: Vec<OtaSessionDto> =
+                    sessions.into_iter().map(OtaSessionDto::from).collect();
+                Ok(HttpResponse::Ok().json(sessions))
+            }
+            Err(e) => {
+                error!("Error while getting ota sessions {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
@@ -0,0 +1,54 @@
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse};
+use futures::future::Future;
+use http::StatusCode;
+
+use crate::api::index::{invalid_request, AppState};
+use crate::handler::ping::*;
+
+pub fn ping_node(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let node_id = match req.match_info().get("node_id").map(|id| id.parse::<i32>()) {
+        Some(Ok(value)) => value,
+        _ => return invalid_request("node_id should be a number"),
+    };
+    let reset_sender = req.state().reset_sender.clone();
+    req.state()
+        .db
+        .send(StartPing { node_id })
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(Some(ping)) => {
+                reset_sender
+                    .send(format!("{};255;3;0;24;1\n", node_id))
+                    .unwrap();
+                Ok(HttpResponse::Ok().json(ping))
+            }
+            Ok(None) => Ok(
+                HttpResponse::build(StatusCode::from_u16(400).unwrap())
+                    .body("Node not present"),
+            ),
+            Err(e) => {
+                error!("Error while pinging node {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+pub fn node_history(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let node_id = match req.match_info().get("node_id").map(|id| id.parse::<i32>()) {
+        Some(Ok(value)) => value,
+        _ => return invalid_request("node_id should be a number"),
+    };
+    req.state()
+        .db
+        .send(GetPings { node_id })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(pings) => Ok(HttpResponse::Ok().json(pings)),
+            Err(e) => {
+                error!("Error while getting pings {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
@@ -0,0 +1,67 @@
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Json};
+use futures::future::Future;
+use http::StatusCode;
+
+use crate::api::index::{invalid_request, AppState};
+use crate::core::node_id_policy::{MAX_NODE_ID, MIN_NODE_ID};
+use crate::handler::reservation::*;
+
+pub fn list(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    req.state()
+        .db
+        .send(ListReservations)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(reservations) => Ok(HttpResponse::Ok().json(reservations)),
+            Err(e) => {
+                error!("Error while getting node id reservations {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+pub fn create(
+    (req, reservation): (HttpRequest<AppState>, Json<NewReservation>),
+) -> FutureResponse<HttpResponse> {
+    if reservation.node_id < i32::from(MIN_NODE_ID) || reservation.node_id > i32::from(MAX_NODE_ID) {
+        return invalid_request("node_id should be between 1 and 254");
+    }
+    if req.state().node_id_policy.is_blocked(reservation.node_id as u8) {
+        return invalid_request("node_id is blocked");
+    }
+    req.state()
+        .db
+        .send(NewReservation {
+            node_id: reservation.node_id,
+            note: reservation.note.clone(),
+        })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(HttpResponse::build(StatusCode::from_u16(msg.status).unwrap()).json(msg)),
+            Err(e) => {
+                error!("Error while reserving node id {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+pub fn delete(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    let node_id = match req.match_info().get("node_id").map(|id| id.parse::<i32>()) {
+        Some(Ok(value)) => value,
+        _ => return invalid_request("node_id should be a number"),
+    };
+    req.state()
+        .db
+        .send(DeleteReservation { node_id })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(HttpResponse::build(StatusCode::from_u16(msg.status).unwrap()).json(msg)),
+            Err(e) => {
+                error!("Error while deleting node id reservation {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
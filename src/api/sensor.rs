@@ -1,8 +1,13 @@
-use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Json};
-use crate::api::index::AppState;
+use std::collections::HashMap;
+
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Json, Query};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use crate::api::index::{invalid_request, AppState};
+use crate::core::message::set::SetReqType;
 use crate::handler::sensor::*;
 use futures::future::Future;
 use http::StatusCode;
+use num::FromPrimitive;
 
 pub fn list(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
     req.state()
@@ -57,3 +62,80 @@ pub fn get_sensor(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
         })
         .responder()
 }
+
+pub fn get_values(
+    (req, query): (HttpRequest<AppState>, Query<HashMap<String, String>>),
+) -> FutureResponse<HttpResponse> {
+    let node_id = match req.match_info().get("node_id").map(|id| id.parse::<i32>()) {
+        Some(Ok(value)) => value,
+        _ => return invalid_request("node_id should be a number"),
+    };
+    let child_sensor_id = match req.match_info().get("child_sensor_id").map(|id| id.parse::<i32>()) {
+        Some(Ok(value)) => value,
+        _ => return invalid_request("child_sensor_id should be a number"),
+    };
+    let to = match query.get("to") {
+        Some(to) => match parse_time(to) {
+            Some(value) => value,
+            None => return invalid_request("to should be a timestamp like 2019-01-31T10:00:00Z"),
+        },
+        None => Utc::now().naive_utc(),
+    };
+    let from = match query.get("from") {
+        Some(from) => match parse_time(from) {
+            Some(value) => value,
+            None => return invalid_request("from should be a timestamp like 2019-01-31T10:00:00Z"),
+        },
+        None => to - Duration::days(1),
+    };
+    if from > to {
+        return invalid_request("from should not be after to");
+    }
+    let set_type = match query.get("type") {
+        Some(set_type) => match parse_set_type(set_type) {
+            Some(value) => Some(value),
+            None => return invalid_request("type should be a set type name or number"),
+        },
+        None => None,
+    };
+    let bucket = match query.get("bucket") {
+        Some(bucket) => match bucket.parse::<i64>() {
+            Ok(value) if value > 0 => Some(value),
+            _ => return invalid_request("bucket should be a positive number of seconds"),
+        },
+        None => None,
+    };
+    req.state()
+        .db
+        .send(GetSensorValues {
+            node_id,
+            child_sensor_id,
+            from,
+            to,
+            set_type,
+            bucket,
+        })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(values) => Ok(HttpResponse::Ok().json(values)),
+            Err(e) => {
+                error!("Error while getting sensor values {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+}
+
+fn parse_set_type(value: &str) -> Option<SetReqType> {
+    match value.parse::<u8>() {
+        Ok(number) => SetReqType::from_u8(number),
+        Err(_) => serde_json::from_value(json!(value)).ok(),
+    }
+}
@@ -0,0 +1,63 @@
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse};
+use futures::future::Future;
+
+use crate::api::index::AppState;
+use crate::handler::node::ListNodes;
+use crate::model::topology;
+
+pub fn get_topology(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    req.state()
+        .db
+        .send(ListNodes)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(nodes) => Ok(HttpResponse::Ok().json(topology::build(&nodes))),
+            Err(e) => {
+                error!("Error while getting network topology {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+pub fn export_dot(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    req.state()
+        .db
+        .send(ListNodes)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(nodes) => Ok(HttpResponse::Ok()
+                .content_type("text/vnd.graphviz")
+                .body(topology::to_dot(&nodes))),
+            Err(e) => {
+                error!("Error while exporting network topology {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+pub fn export_graph(req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    req.state()
+        .db
+        .send(ListNodes)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(nodes) => Ok(HttpResponse::Ok().json(topology::graph(&nodes))),
+            Err(e) => {
+                error!("Error while exporting network topology {:?}", e);
+                Ok(HttpResponse::InternalServerError().into())
+            }
+        })
+        .responder()
+}
+
+pub fn discover(req: &HttpRequest<AppState>) -> HttpResponse {
+    match req.state().reset_sender.send(String::from("255;255;3;0;20;\n")) {
+        Ok(_) => HttpResponse::Ok().body("Sent discover request to all nodes"),
+        Err(e) => {
+            error!("Error while sending discover request {:?}", e);
+            HttpResponse::InternalServerError().into()
+        }
+    }
+}
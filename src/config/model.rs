@@ -22,6 +22,15 @@ pub struct Controller {
 pub struct Server {
     pub database_url: Option<String>,
     pub log_level: Option<String>,
+    pub node_timeout_secs: Option<String>,
+    pub unit_system: Option<String>,
+    pub inclusion_always_open: Option<String>,
+    pub reserved_node_ids: Option<String>,
+    pub blocked_node_ids: Option<String>,
+    pub node_id_reuse_after_days: Option<String>,
+    pub auto_rollback_after_secs: Option<String>,
+    pub max_concurrent_ota: Option<String>,
+    pub ota_window: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
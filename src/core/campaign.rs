@@ -0,0 +1,278 @@
+use std::thread;
+use std::thread::JoinHandle;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+
+use crate::model::campaign::{Campaign, CampaignNode, CampaignStatus};
+use crate::model::node::Node;
+use crate::model::ota_session::OtaStatus;
+
+const CHECK_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, PartialEq)]
+pub struct CanaryState {
+    pub runs_target: bool,
+    /// When the canary started running the target firmware, `None` if it already ran it
+    /// before the campaign.
+    pub updated_at: Option<NaiveDateTime>,
+    pub last_set_at: Option<NaiveDateTime>,
+    pub last_boot_at: Option<NaiveDateTime>,
+    pub timeout: Duration,
+}
+
+impl CanaryState {
+    /// A canary is healthy once it runs the target firmware, kept sending values for
+    /// the whole soak period after the update and still does. A reboot since the update,
+    /// which any later firmware change also involves, makes it unhealthy.
+    /// A canary that already ran the target before the campaign tells nothing about the
+    /// update, so it doesn't hold the promotion back.
+    pub fn is_healthy(&self, soak: Duration, now: NaiveDateTime) -> bool {
+        match (self.updated_at, self.last_set_at) {
+            (None, _) if self.runs_target => true,
+            (Some(updated_at), Some(last_set_at)) => {
+                let rebooted = match self.last_boot_at {
+                    Some(last_boot_at) => last_boot_at > updated_at,
+                    None => false,
+                };
+                self.runs_target
+                    && !rebooted
+                    && last_set_at >= updated_at + soak
+                    && now - last_set_at <= self.timeout
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Periodically promotes running campaigns whose canaries are healthy
+/// and completes the ones whose nodes all run the target firmware.
+pub fn monitor(pool: Pool<ConnectionManager<SqliteConnection>>, node_timeout_secs: i64) -> JoinHandle<()> {
+    thread::spawn(move || loop {
+        match pool.get() {
+            Ok(connection) => check_campaigns(&connection, node_timeout_secs),
+            Err(e) => error!("Error while getting connection for campaigns {:?}", e),
+        }
+        thread::sleep(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+    })
+}
+
+fn check_campaigns(connection: &SqliteConnection, node_timeout_secs: i64) {
+    use crate::model::campaign::campaigns::dsl::*;
+    match campaigns
+        .filter(status.eq(CampaignStatus::Running))
+        .load::<Campaign>(connection)
+        {
+            Ok(running_campaigns) => {
+                for campaign in running_campaigns {
+                    match progress_campaign(connection, &campaign, node_timeout_secs) {
+                        Ok(_) => (),
+                        Err(e) => error!("Error while checking campaign {} {:?}", campaign.id, e),
+                    }
+                }
+            }
+            Err(e) => error!("Error while loading running campaigns {:?}", e),
+        }
+}
+
+fn progress_campaign(
+    connection: &SqliteConnection,
+    campaign: &Campaign,
+    node_timeout_secs: i64,
+) -> Result<(), diesel::result::Error> {
+    use crate::model::campaign::campaigns::dsl::*;
+    let now = Utc::now().naive_utc();
+    let members = campaign_members(connection, campaign.id)?;
+    let member_ids: Vec<i32> = members.iter().map(|member| member.node_id).collect();
+    let member_nodes = crate::model::node::nodes::table
+        .filter(crate::model::node::nodes::node_id.eq_any(&member_ids))
+        .load::<Node>(connection)?;
+    if campaign.promoted_at.is_none() {
+        let mut canaries = Vec::new();
+        for member in members.iter().filter(|member| member.canary) {
+            let node = member_nodes.iter().find(|node| node.node_id == member.node_id);
+            canaries.push(canary_state(connection, campaign, node, node_timeout_secs)?);
+        }
+        let soak = Duration::minutes(i64::from(campaign.soak_minutes));
+        if canaries.iter().all(|canary| canary.is_healthy(soak, now)) {
+            let remaining_ids: Vec<i32> = members
+                .iter()
+                .filter(|member| !member.canary)
+                .map(|member| member.node_id)
+                .collect();
+            schedule_update(connection, &remaining_ids, campaign.firmware_type, campaign.firmware_version)?;
+            diesel::update(campaigns.find(campaign.id))
+                .set(promoted_at.eq(now))
+                .execute(connection)?;
+            info!("Promoted campaign {} to {} nodes", campaign.id, remaining_ids.len());
+        }
+    } else if member_nodes.iter().all(|node| runs_firmware(node, campaign)) {
+        diesel::update(campaigns.find(campaign.id))
+            .set((status.eq(CampaignStatus::Completed), finished_at.eq(now)))
+            .execute(connection)?;
+        info!("Campaign {} completed", campaign.id);
+    }
+    Ok(())
+}
+
+fn canary_state(
+    connection: &SqliteConnection,
+    campaign: &Campaign,
+    node: Option<&Node>,
+    node_timeout_secs: i64,
+) -> Result<CanaryState, diesel::result::Error> {
+    let node = match node {
+        Some(node) => node,
+        None => {
+            return Ok(CanaryState {
+                runs_target: false,
+                updated_at: None,
+                last_set_at: None,
+                last_boot_at: None,
+                timeout: Duration::seconds(node_timeout_secs),
+            })
+        }
+    };
+    let runs_target = runs_firmware(node, campaign);
+    let completed_at = {
+        use crate::model::ota_session::ota_sessions::dsl::*;
+        ota_sessions
+            .select(finished_at)
+            .filter(node_id.eq(node.node_id))
+            .filter(firmware_type.eq(campaign.firmware_type))
+            .filter(firmware_version.eq(campaign.firmware_version))
+            .filter(status.eq(OtaStatus::Completed))
+            .filter(started_at.ge(campaign.created_at))
+            .order(id.desc())
+            .first::<Option<NaiveDateTime>>(connection)
+            .optional()?
+            .and_then(|completed_at| completed_at)
+    };
+    let last_set_at = {
+        use crate::model::sensor_value::sensor_values::dsl::*;
+        sensor_values
+            .select(diesel::dsl::max(received_at))
+            .filter(node_id.eq(node.node_id))
+            .first::<Option<NaiveDateTime>>(connection)?
+    };
+    Ok(CanaryState {
+        runs_target,
+        updated_at: completed_at,
+        last_set_at,
+        last_boot_at: node.last_boot_at,
+        timeout: node.timeout(node_timeout_secs),
+    })
+}
+
+fn runs_firmware(node: &Node, campaign: &Campaign) -> bool {
+    node.firmware_type == campaign.firmware_type && node.firmware_version == campaign.firmware_version
+}
+
+pub fn campaign_members(
+    connection: &SqliteConnection,
+    _campaign_id: i32,
+) -> Result<Vec<CampaignNode>, diesel::result::Error> {
+    use crate::model::campaign::campaign_nodes::dsl::*;
+    campaign_nodes
+        .filter(campaign_id.eq(_campaign_id))
+        .order(node_id.asc())
+        .load::<CampaignNode>(connection)
+}
+
+/// Sets the desired firmware of the given nodes, they are updated on their next reboot.
+pub fn schedule_update(
+    connection: &SqliteConnection,
+    node_ids: &[i32],
+    _firmware_type: i32,
+    _firmware_version: i32,
+) -> Result<usize, diesel::result::Error> {
+    use crate::model::node::nodes::dsl::*;
+    diesel::update(nodes)
+        .filter(node_id.eq_any(node_ids))
+        .set((
+            desired_firmware_type.eq(_firmware_type),
+            desired_firmware_version.eq(_firmware_version),
+            scheduled.eq(true),
+        ))
+        .execute(connection)
+}
+
+/// Reverts the desired firmware of the given nodes not yet running the firmware
+/// to the one they run, so they are not updated anymore.
+pub fn cancel_update(
+    connection: &SqliteConnection,
+    node_ids: &[i32],
+    _firmware_type: i32,
+    _firmware_version: i32,
+) -> Result<usize, diesel::result::Error> {
+    use crate::model::node::nodes::dsl::*;
+    diesel::update(nodes)
+        .filter(node_id.eq_any(node_ids))
+        .filter(desired_firmware_type.eq(_firmware_type))
+        .filter(desired_firmware_version.eq(_firmware_version))
+        .filter(firmware_type.ne(_firmware_type).or(firmware_version.ne(_firmware_version)))
+        .set((
+            desired_firmware_type.eq(firmware_type),
+            desired_firmware_version.eq(firmware_version),
+            scheduled.eq(false),
+        ))
+        .execute(connection)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(minutes: i64) -> Option<NaiveDateTime> {
+        Some(NaiveDateTime::from_timestamp(minutes * 60, 0))
+    }
+
+    #[test]
+    fn canary_is_healthy_after_sending_values_for_soak_period() {
+        let soak = Duration::minutes(30);
+        let now = at(35).unwrap();
+        let canary = |runs_target, updated_at, last_set_at| CanaryState {
+            runs_target,
+            updated_at,
+            last_set_at,
+            last_boot_at: updated_at,
+            timeout: Duration::minutes(10),
+        };
+        assert!(canary(true, at(0), at(30)).is_healthy(soak, now));
+        assert!(!canary(true, at(0), at(29)).is_healthy(soak, now));
+        assert!(!canary(true, at(0), None).is_healthy(soak, now));
+        assert!(!canary(false, at(0), at(40)).is_healthy(soak, now));
+        assert!(!canary(false, None, at(40)).is_healthy(soak, now));
+    }
+
+    #[test]
+    fn canary_gone_quiet_or_rebooted_is_not_healthy() {
+        let soak = Duration::minutes(30);
+        let canary = |last_boot_at| CanaryState {
+            runs_target: true,
+            updated_at: at(0),
+            last_set_at: at(30),
+            last_boot_at,
+            timeout: Duration::minutes(10),
+        };
+        assert!(canary(None).is_healthy(soak, at(40).unwrap()));
+        assert!(!canary(None).is_healthy(soak, at(41).unwrap()));
+        assert!(!canary(at(20)).is_healthy(soak, at(35).unwrap()));
+    }
+
+    #[test]
+    fn canary_already_running_target_does_not_hold_promotion_back() {
+        let soak = Duration::minutes(30);
+        let canary = |runs_target, last_boot_at| CanaryState {
+            runs_target,
+            updated_at: None,
+            last_set_at: at(5),
+            last_boot_at,
+            timeout: Duration::minutes(10),
+        };
+        assert!(canary(true, at(20)).is_healthy(soak, at(60).unwrap()));
+        assert!(!canary(false, None).is_healthy(soak, at(60).unwrap()));
+    }
+}
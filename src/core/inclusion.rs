@@ -0,0 +1,137 @@
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::channel::Sender;
+
+pub const DEFAULT_INCLUSION_DURATION: Duration = Duration::from_secs(60);
+pub const MAX_INCLUSION_DURATION: Duration = Duration::from_secs(3600);
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct InclusionState {
+    pub open: bool,
+    pub always_open: bool,
+    pub remaining_secs: Option<u64>,
+}
+
+/// Inclusion window shared between the api and the message handlers.
+/// New node ids are handed out and new nodes are created only while it is open.
+#[derive(Clone, Debug)]
+pub struct Inclusion {
+    always_open: bool,
+    open_until: Arc<RwLock<Option<Instant>>>,
+}
+
+impl Inclusion {
+    pub fn new(always_open: bool) -> Inclusion {
+        Inclusion {
+            always_open,
+            open_until: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.always_open || self.remaining().is_some()
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        let now = Instant::now();
+        match *self.open_until.read().unwrap() {
+            Some(open_until) if open_until > now => Some(open_until - now),
+            _ => None,
+        }
+    }
+
+    pub fn state(&self) -> InclusionState {
+        InclusionState {
+            open: self.is_open(),
+            always_open: self.always_open,
+            remaining_secs: self.remaining().map(|remaining| remaining.as_secs()),
+        }
+    }
+
+    pub fn open(&self, duration: Duration) -> Result<(), String> {
+        let open_until = Instant::now()
+            .checked_add(duration)
+            .ok_or_else(|| format!("inclusion duration of {} seconds is too long", duration.as_secs()))?;
+        *self.open_until.write().unwrap() = Some(open_until);
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        *self.open_until.write().unwrap() = None;
+    }
+
+    /// Opens the window and puts the gateway in inclusion mode,
+    /// the gateway is told to stop once the window expires.
+    pub fn start(&self, duration: Duration, gateway_sender: &Sender<String>) -> Result<(), String> {
+        self.open(duration)?;
+        send_inclusion_mode(gateway_sender, true);
+        let inclusion = self.clone();
+        let gateway_sender = gateway_sender.clone();
+        thread::spawn(move || {
+            thread::sleep(duration);
+            if inclusion.remaining().is_none() {
+                info!("Inclusion mode expired");
+                send_inclusion_mode(&gateway_sender, false);
+            }
+        });
+        Ok(())
+    }
+
+    pub fn stop(&self, gateway_sender: &Sender<String>) {
+        self.close();
+        send_inclusion_mode(gateway_sender, false);
+    }
+}
+
+fn send_inclusion_mode(gateway_sender: &Sender<String>, enabled: bool) {
+    match gateway_sender.send(format!("0;255;3;0;5;{}\n", enabled as u8)) {
+        Ok(_) => (),
+        Err(e) => error!("Error while sending inclusion mode to gateway {:?}", e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn inclusion_is_closed_until_opened() {
+        let inclusion = Inclusion::new(false);
+        assert!(!inclusion.is_open());
+        inclusion.open(Duration::from_secs(60)).unwrap();
+        assert!(inclusion.is_open());
+        assert!(inclusion.remaining().unwrap() <= Duration::from_secs(60));
+        inclusion.close();
+        assert!(!inclusion.is_open());
+    }
+
+    #[test]
+    fn inclusion_expires() {
+        let inclusion = Inclusion::new(false);
+        inclusion.open(Duration::from_millis(0)).unwrap();
+        assert!(!inclusion.is_open());
+    }
+
+    #[test]
+    fn overflowing_inclusion_duration_is_rejected() {
+        let inclusion = Inclusion::new(false);
+        assert!(inclusion.open(Duration::from_secs(u64::max_value())).is_err());
+        assert!(!inclusion.is_open());
+    }
+
+    #[test]
+    fn always_open_inclusion() {
+        let inclusion = Inclusion::new(true);
+        assert!(inclusion.is_open());
+        assert_eq!(
+            inclusion.state(),
+            InclusionState {
+                open: true,
+                always_open: true,
+                remaining_secs: None,
+            }
+        );
+    }
+}
@@ -1,13 +1,22 @@
-use super::message::{presentation::*, set::*, stream::*, internal::*, CommandMessage};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::ConnectionManager;
+use r2d2::*;
+
+use super::message::{presentation::*, req::*, set::*, stream::*, internal::*, CommandMessage};
+use super::message_handler::internal::update_last_seen;
 use crate::channel::{Receiver, Sender};
 
+#[allow(clippy::too_many_arguments)]
 pub fn intercept(
     receiver: &Receiver<String>,
     stream_sender: &Sender<StreamMessage>,
     internal_sender: &Sender<InternalMessage>,
     presentation_sender: &Sender<PresentationMessage>,
     set_sender: &Sender<SetMessage>,
+    req_sender: &Sender<ReqMessage>,
     controller_sender: &Sender<String>,
+    db_connection: PooledConnection<ConnectionManager<SqliteConnection>>,
 ) {
     // let node_id_request: String = "255;255;3;0;3;0\n".to_string(); TODO
     loop {
@@ -27,6 +36,17 @@ pub fn intercept(
         // }
         let command_message_result = CommandMessage::new(&request);
 
+        if let Some(node_id) = command_message_result
+            .as_ref()
+            .ok()
+            .and_then(|command_message| command_message.node_id())
+            {
+                match update_last_seen(&db_connection, i32::from(node_id), Utc::now().naive_utc()) {
+                    Ok(_) => (),
+                    Err(e) => error!("Error while updating last seen for node {} {:?}", node_id, e),
+                }
+            }
+
         match command_message_result {
             Ok(command_message) => match command_message {
                 CommandMessage::Stream(stream_message) => match stream_sender.send(stream_message) {
@@ -46,6 +66,10 @@ pub fn intercept(
                     Ok(_) => (),
                     Err(error) => error!("Error while sending to set_sender {:?}", error),
                 },
+                CommandMessage::Req(req_message) => match req_sender.send(req_message) {
+                    Ok(_) => (),
+                    Err(error) => error!("Error while sending to req_sender {:?}", error),
+                },
                 _ => match controller_sender.send(request) {
                     Ok(_) => (),
                     Err(error) => error!("Error while sending to controller {:?}", error),
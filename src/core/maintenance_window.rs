@@ -0,0 +1,86 @@
+use chrono::NaiveTime;
+
+/// Time of day range during which nodes may be offered a new firmware.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaintenanceWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl MaintenanceWindow {
+    /// Windows ending before they start span midnight, like 23:00-02:00.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+/// Whether a firmware may be offered at `time`, always allowed without windows.
+pub fn is_open(windows: &[MaintenanceWindow], time: NaiveTime) -> bool {
+    windows.is_empty() || windows.iter().any(|window| window.contains(time))
+}
+
+/// Parses windows like "02:00-05:00,13:30-14:00".
+pub fn parse_windows(value: &str) -> Result<Vec<MaintenanceWindow>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut bounds = part.splitn(2, '-').map(str::trim);
+            let start = parse_time(bounds.next().unwrap_or(""))?;
+            let end = match bounds.next() {
+                Some(end) => parse_time(end)?,
+                None => return Err(format!("invalid window {}, should be like 02:00-05:00", part)),
+            };
+            if start == end {
+                return Err(format!("invalid window {}, start and end are the same", part));
+            }
+            Ok(MaintenanceWindow { start, end })
+        })
+        .collect()
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| format!("invalid time {}, should be like 02:00", value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn parse_maintenance_windows() {
+        assert_eq!(
+            Ok(vec![
+                MaintenanceWindow { start: time(2, 0), end: time(5, 0) },
+                MaintenanceWindow { start: time(23, 30), end: time(1, 0) },
+            ]),
+            parse_windows("02:00-05:00, 23:30-01:00")
+        );
+        assert_eq!(Ok(vec![]), parse_windows(""));
+        assert!(parse_windows("02:00").is_err());
+        assert!(parse_windows("02:00-02:00").is_err());
+        assert!(parse_windows("25:00-05:00").is_err());
+    }
+
+    #[test]
+    fn windows_contain_times_including_across_midnight() {
+        let windows = parse_windows("02:00-05:00,23:00-00:30").unwrap();
+        assert!(is_open(&windows, time(2, 0)));
+        assert!(is_open(&windows, time(4, 59)));
+        assert!(!is_open(&windows, time(5, 0)));
+        assert!(!is_open(&windows, time(20, 0)));
+        assert!(is_open(&windows, time(23, 15)));
+        assert!(is_open(&windows, time(0, 10)));
+        assert!(!is_open(&windows, time(0, 30)));
+        assert!(is_open(&[], time(20, 0)));
+    }
+}
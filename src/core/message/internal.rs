@@ -39,6 +39,31 @@ enum_from_primitive! {
     }
 }
 
+#[derive(DbEnum, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    Metric,
+    Imperial,
+}
+
+impl UnitSystem {
+    pub fn from_name(name: &str) -> Option<UnitSystem> {
+        match name.to_lowercase().as_str() {
+            "metric" => Some(UnitSystem::Metric),
+            "imperial" => Some(UnitSystem::Imperial),
+            _ => None,
+        }
+    }
+
+    /// Payload of the I_CONFIG response, (M)etric or (I)mperial.
+    pub fn config_payload(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "M",
+            UnitSystem::Imperial => "I",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct InternalMessage {
     pub node_id: u8,
@@ -89,3 +114,24 @@ impl fmt::Display for InternalMessage {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unit_system_from_config() {
+        assert_eq!(UnitSystem::from_name("metric"), Some(UnitSystem::Metric));
+        assert_eq!(UnitSystem::from_name("Imperial"), Some(UnitSystem::Imperial));
+        assert_eq!(UnitSystem::from_name("kelvin"), None);
+    }
+
+    #[test]
+    fn config_response() {
+        let mut message = InternalMessage::build(1, 255, 6, 0, "").unwrap();
+        assert_eq!(
+            message.as_response(UnitSystem::Imperial.config_payload().to_owned()),
+            "1;255;3;0;6;I\n"
+        );
+    }
+}
@@ -1,6 +1,7 @@
 pub mod error;
 pub mod presentation;
 pub mod internal;
+pub mod req;
 pub mod set;
 pub mod stream;
 
@@ -23,7 +24,7 @@ enum_from_primitive! {
 pub enum CommandMessage {
     Presentation(presentation::PresentationMessage),
     Set(set::SetMessage),
-    // Req(ReqMessage),
+    Req(req::ReqMessage),
     Internal(internal::InternalMessage),
     Other(String),
     Stream(stream::StreamMessage),
@@ -87,16 +88,35 @@ impl CommandMessage {
                 sub_type,
                 payload,
             )?),
-            _ => CommandMessage::Other(command_message.to_owned()),
+            CommandType::REQ => CommandMessage::Req(req::ReqMessage::build(
+                node_id,
+                child_sensor_id,
+                ack,
+                sub_type,
+            )?),
         })
     }
 }
 
+impl CommandMessage {
+    pub fn node_id(&self) -> Option<u8> {
+        match *self {
+            CommandMessage::Presentation(ref message) => Some(message.node_id),
+            CommandMessage::Set(ref message) => Some(message.node_id),
+            CommandMessage::Req(ref message) => Some(message.node_id),
+            CommandMessage::Internal(ref message) => Some(message.node_id),
+            CommandMessage::Stream(ref message) => Some(message.node_id),
+            CommandMessage::Other(_) => None,
+        }
+    }
+}
+
 impl fmt::Display for CommandMessage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             CommandMessage::Presentation(ref message) => write!(f, "{}", message.to_string()),
             CommandMessage::Set(ref message) => write!(f, "{}", message.to_string()),
+            CommandMessage::Req(ref message) => write!(f, "{}", message),
             CommandMessage::Internal(ref message) => write!(f, "{}", message.to_string()),
             CommandMessage::Stream(ref message) => write!(f, "{}", message.to_string()),
             CommandMessage::Other(ref message) => write!(f, "{}", message),
@@ -125,7 +145,7 @@ pub fn command_type(message_string: &str) -> Option<CommandType> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::model::firmware::Firmware;
+    use crate::model::firmware::{Board, Firmware};
     use std::path::PathBuf;
 
     #[test]
@@ -216,6 +236,26 @@ mod test {
         }
     }
 
+    #[test]
+    fn parse_correct_command_req() {
+        let message_string = "1;2;2;0;2;\n";
+        if let Ok(CommandMessage::Req(message)) = CommandMessage::new(&String::from(message_string))
+        {
+            assert_eq!(message.node_id, 1);
+            assert_eq!(message.child_sensor_id, 2);
+            assert_eq!(message.sub_type, set::SetReqType::Status);
+        } else {
+            assert!(false, "Didn't parse to Req message");
+        }
+    }
+
+    #[test]
+    fn format_req() {
+        let message_string = "1;2;2;0;2;\n";
+        let command_message = CommandMessage::new(&String::from(message_string)).unwrap();
+        assert_eq!(command_message.to_string(), String::from(message_string));
+    }
+
     #[test]
     fn format_fw_config_request() {
         let message_string = "1;255;4;0;0;0A0001005000D4460102\n";
@@ -257,6 +297,12 @@ mod test {
                 crc: 1000,
                 data: vec![],
                 name: String::from("Blink.hex"),
+                board: Board::Atmega328,
+                description: None,
+                uploaded_at: None,
+                sha256: None,
+                filename: None,
+                git_revision: None,
             });
             assert_eq!(
                 message.to_string(),
@@ -267,18 +313,30 @@ mod test {
         }
     }
 
+    #[test]
+    fn convert_fw_config_request_to_current_firmware_response() {
+        let message_string = "1;255;4;0;0;0A0001005000D4460102\n";
+        if let Ok(CommandMessage::Stream(mut message)) =
+            CommandMessage::new(&String::from(message_string))
+        {
+            message.keep_current_firmware();
+            assert_eq!(
+                message.to_string(),
+                String::from("1;255;4;0;1;0A0001005000D446\n")
+            );
+        } else {
+            assert!(false, "Didn't parse to Stream message");
+        }
+    }
+
     #[test]
     fn convert_fw_request_to_response() {
         let message_string = "1;255;4;0;2;0A0002000700\n";
         if let Ok(CommandMessage::Stream(mut message)) =
             CommandMessage::new(&String::from(message_string))
         {
-            message.response(&Firmware::prepare_fw(
-                10,
-                2,
-                String::from("Blink"),
-                &PathBuf::from("firmwares/10__2__Blink.ino.hex"),
-            ).unwrap());
+            let image = Firmware::prepare_fw(Board::Atmega328, &PathBuf::from("firmwares/10__2__Blink.ino.hex")).unwrap();
+            message.response(&Firmware::build(10, 2, String::from("Blink"), Board::Atmega328, image.data).unwrap());
             assert_eq!(
                 message.to_string(),
                 String::from("1;255;4;0;3;0A000200070000030407000000000000000001020408\n")
@@ -50,6 +50,10 @@ enum_from_primitive! {
 }
 
 impl PresentationType {
+    pub fn is_node(self) -> bool {
+        self == PresentationType::ArduinoNode || self == PresentationType::ArduinoRepeaterNode
+    }
+
     pub fn is_supported(self) -> bool {
         !(self.thing_type().is_empty() || self.thing_description().is_empty()
             || !(self.property_types()
@@ -157,6 +161,13 @@ impl fmt::Display for PresentationMessage {
 mod test {
     use super::*;
 
+    #[test]
+    fn node_presentation_types() {
+        assert!(PresentationType::ArduinoNode.is_node());
+        assert!(PresentationType::ArduinoRepeaterNode.is_node());
+        assert!(!PresentationType::Temp.is_node());
+    }
+
     #[test]
     fn supported_sensor_types() {
         assert!(PresentationType::Door.is_supported());
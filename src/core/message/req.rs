@@ -0,0 +1,79 @@
+use super::error::ParseError;
+use super::set::{SetMessage, SetReqType, Value};
+use num::FromPrimitive;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ReqMessage {
+    pub node_id: u8,
+    pub child_sensor_id: u8,
+    pub ack: u8,
+    pub sub_type: SetReqType,
+}
+
+impl ReqMessage {
+    pub fn build(
+        node_id: u8,
+        child_sensor_id: u8,
+        ack: u8,
+        sub_type: u8,
+    ) -> Result<ReqMessage, ParseError> {
+        let sub_type = SetReqType::from_u8(sub_type).ok_or(ParseError::InvalidSubType)?;
+        Ok(ReqMessage {
+            node_id,
+            child_sensor_id,
+            ack,
+            sub_type,
+        })
+    }
+
+    pub fn as_response(&self, value: String) -> SetMessage {
+        SetMessage {
+            node_id: self.node_id,
+            child_sensor_id: self.child_sensor_id,
+            ack: 0,
+            value: Value {
+                set_type: self.sub_type,
+                value,
+            },
+        }
+    }
+}
+
+impl fmt::Display for ReqMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let _cmd = 2;
+        writeln!(
+            f,
+            "{};{};{};{};{};",
+            self.node_id, self.child_sensor_id, _cmd, self.ack, self.sub_type as u8
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn req_message_display_method() {
+        assert_eq!(
+            "1;2;2;0;2;\n",
+            ReqMessage {
+                node_id: 1,
+                child_sensor_id: 2,
+                ack: 0,
+                sub_type: SetReqType::Status,
+            }.to_string()
+        )
+    }
+
+    #[test]
+    fn convert_req_to_set_response() {
+        let req_message = ReqMessage::build(1, 2, 0, 2).unwrap();
+        assert_eq!(
+            "1;2;1;0;2;1\n",
+            req_message.as_response("1".to_owned()).to_string()
+        )
+    }
+}
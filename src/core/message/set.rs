@@ -79,6 +79,10 @@ impl Value {
         }
     }
 
+    pub fn numeric_value(&self) -> Option<f64> {
+        self.value.trim().parse::<f64>().ok()
+    }
+
     pub fn build(set_type: SetReqType, value: serde_json::Value) -> Option<Value> {
         let value = match set_type.data_type() {
             "boolean" => match value {
@@ -97,7 +101,7 @@ impl Value {
 }
 
 enum_from_primitive! {
-    #[derive(Debug, PartialEq, Clone, Copy)]
+    #[derive(DbEnum, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
     pub enum SetReqType {
         Temp = 0,
         Hum = 1,
@@ -255,6 +259,17 @@ mod test {
         assert!(SetReqType::Pressure.is_supported());
     }
 
+    #[test]
+    fn numeric_value_of_set_message() {
+        let value = |set_type, value: &str| Value {
+            set_type,
+            value: value.to_owned(),
+        };
+        assert_eq!(Some(21.5), value(SetReqType::Temp, "21.5").numeric_value());
+        assert_eq!(Some(1.0), value(SetReqType::Status, "1").numeric_value());
+        assert_eq!(None, value(SetReqType::Text, "hello").numeric_value());
+    }
+
     #[test]
     fn set_message_display_method() {
         assert_eq!(
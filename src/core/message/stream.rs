@@ -86,6 +86,20 @@ impl StreamMessage {
             _ => self.payload,
         };
     }
+
+    /// Answers a firmware config request with the firmware the node already runs,
+    /// so the node boots it instead of starting an update.
+    pub fn keep_current_firmware(&mut self) {
+        if let StreamPayload::FwConfigRequest(request) = self.payload {
+            self.sub_type = StreamType::StFirmwareConfigResponse;
+            self.payload = StreamPayload::FwConfigResponse(FwConfigResponseMessage {
+                firmware_type: request.firmware_type,
+                firmware_version: request.firmware_version,
+                blocks: request.blocks,
+                crc: request.crc,
+            });
+        }
+    }
 }
 
 impl fmt::Display for StreamMessage {
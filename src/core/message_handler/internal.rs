@@ -1,30 +1,67 @@
+use std::convert::TryFrom;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use chrono::{Duration, NaiveDateTime, Utc};
 use diesel;
 use diesel::prelude::*;
 use diesel::r2d2::ConnectionManager;
 use r2d2::*;
 
 use crate::channel::{Receiver, Sender};
+use crate::core::inclusion::{Inclusion, DEFAULT_INCLUSION_DURATION};
 use crate::core::message::internal::*;
+use crate::core::node_id_policy::NodeIdPolicy;
+use crate::model::battery_level::NewBatteryLevel;
+use crate::model::battery_level::battery_levels::dsl::battery_levels;
 use crate::model::node::Node;
 use crate::model::node::nodes::dsl;
-
-const MIN_NODE_ID: u8 = 1;
-const MAX_NODE_ID: u8 = 254;
+use crate::model::node_id_reservation::{deleted_nodes, node_id_reservations};
+use crate::model::node_ping::NodePing;
 
 pub fn handle(
     receiver: &Receiver<InternalMessage>,
     response_sender: &Sender<String>,
     controller_forward_sender: &Sender<String>,
     db_connection: PooledConnection<ConnectionManager<SqliteConnection>>,
+    unit_system: UnitSystem,
+    inclusion: &Inclusion,
+    node_id_policy: &NodeIdPolicy,
 ) {
     loop {
         if let Ok(message) = receiver.recv() {
             match message.sub_type {
-                InternalType::IdRequest => send_node_id(&db_connection, response_sender, message),
-                InternalType::SketchName => update_node_name(&db_connection, message),
+                InternalType::IdRequest => {
+                    if inclusion.is_open() {
+                        send_node_id(&db_connection, response_sender, node_id_policy, message)
+                    } else {
+                        warn!("Ignoring node id request as inclusion mode is not active");
+                    }
+                }
+                InternalType::SketchName => update_sketch_name(&db_connection, message),
+                InternalType::SketchVersion => {
+                    update_sketch_version(&db_connection, &message);
+                    forward_to_controller(controller_forward_sender, message)
+                }
                 InternalType::Time => send_current_time(response_sender, message),
+                InternalType::Config => {
+                    send_config(&db_connection, response_sender, unit_system, message)
+                }
+                InternalType::BatteryLevel => {
+                    update_battery_level(&db_connection, &message);
+                    forward_to_controller(controller_forward_sender, message)
+                }
+                InternalType::HeartbeatResponse => {
+                    debug!("Heartbeat response from node {}", message.node_id);
+                    forward_to_controller(controller_forward_sender, message)
+                }
+                InternalType::Pong => {
+                    record_pong(&db_connection, &message);
+                    forward_to_controller(controller_forward_sender, message)
+                }
+                InternalType::InclusionMode => {
+                    update_inclusion_mode(inclusion, &message);
+                    forward_to_controller(controller_forward_sender, message)
+                }
                 InternalType::DiscoverResponse => {
                     send_discover_response(&db_connection, &message);
                     forward_to_controller(controller_forward_sender, message)
@@ -38,29 +75,89 @@ pub fn handle(
 fn send_node_id(
     db_connection: &PooledConnection<ConnectionManager<SqliteConnection>>,
     response_sender: &Sender<String>,
+    node_id_policy: &NodeIdPolicy,
     mut message: InternalMessage,
 ) {
-    match get_next_node_id(db_connection) {
-        Some(new_node_id) => match create_node(db_connection, i32::from(new_node_id)) {
+    match get_next_node_id(db_connection, node_id_policy) {
+        Ok(Some(new_node_id)) => match create_node(db_connection, i32::from(new_node_id)) {
             Ok(_) => match response_sender.send(message.as_response(new_node_id.to_string())) {
                 Ok(_) => (),
                 Err(_) => error!("Error while sending to node_handler"),
             },
             Err(_) => error!("Error while creating node with new id"),
         },
-        None => error!("There is no free node id! All 254 id's are already reserved!"),
+        Ok(None) => error!("There is no free node id! All 254 id's are already used, reserved or blocked!"),
+        Err(e) => error!("Error while looking for a free node id, not assigning one {:?}", e),
     }
 }
 
+/// Sketch names and versions are cut to the longest payload a node can send.
+const MAX_SKETCH_INFO_LENGTH: usize = 25;
+
+fn sketch_info(message: &InternalMessage) -> Option<String> {
+    let info = message.payload.trim();
+    if info.is_empty() {
+        warn!("Ignoring empty {:?} from node {}", message.sub_type, message.node_id);
+        return None;
+    }
+    Some(info.chars().take(MAX_SKETCH_INFO_LENGTH).collect())
+}
+
+fn update_sketch_name(
+    db_connection: &PooledConnection<ConnectionManager<SqliteConnection>>,
+    message: InternalMessage,
+) {
+    use crate::model::node::nodes::dsl::*;
+    let name = match sketch_info(&message) {
+        Some(name) => name,
+        None => return,
+    };
+    match diesel::update(nodes)
+        .filter(node_id.eq(i32::from(message.node_id)))
+        .set(sketch_name.eq(&name))
+        .execute(db_connection)
+        {
+            Ok(_) => (),
+            Err(e) => error!(
+                "Error while trying to update sketch name for {:?} : {:?}",
+                message, e
+            ),
+        }
+    update_node_name(db_connection, message, name);
+}
+
+fn update_sketch_version(
+    db_connection: &PooledConnection<ConnectionManager<SqliteConnection>>,
+    message: &InternalMessage,
+) {
+    use crate::model::node::nodes::dsl::*;
+    let version = match sketch_info(message) {
+        Some(version) => version,
+        None => return,
+    };
+    match diesel::update(nodes)
+        .filter(node_id.eq(i32::from(message.node_id)))
+        .set(sketch_version.eq(version))
+        .execute(db_connection)
+        {
+            Ok(_) => (),
+            Err(e) => error!(
+                "Error while trying to update sketch version for {:?} : {:?}",
+                message, e
+            ),
+        }
+}
+
 fn update_node_name(
     db_connection: &PooledConnection<ConnectionManager<SqliteConnection>>,
     message: InternalMessage,
+    name: String,
 ) {
     use crate::model::node::nodes::dsl::*;
     match diesel::update(nodes)
         .filter(node_id.eq(i32::from(message.node_id)))
         .filter(node_name.eq("New Node".to_owned()))
-        .set(node_name.eq(message.payload.clone()))
+        .set(node_name.eq(name))
         .execute(db_connection)
         {
             Ok(_) => (),
@@ -71,6 +168,91 @@ fn update_node_name(
         }
 }
 
+fn update_battery_level(
+    db_connection: &PooledConnection<ConnectionManager<SqliteConnection>>,
+    message: &InternalMessage,
+) {
+    use crate::model::node::nodes::dsl::*;
+    let level = match message.payload.trim().parse::<u8>() {
+        Ok(level) if level <= 100 => i32::from(level),
+        _ => {
+            error!("Invalid battery level {:?} from node {}", &message.payload, message.node_id);
+            return;
+        }
+    };
+    let new_battery_level = NewBatteryLevel {
+        node_id: i32::from(message.node_id),
+        level,
+        reported_at: Utc::now().naive_utc(),
+    };
+    match diesel::insert_into(battery_levels)
+        .values(&new_battery_level)
+        .execute(db_connection)
+        {
+            Ok(_) => (),
+            Err(e) => error!("Error while storing {:?} : {:?}", new_battery_level, e),
+        }
+    match diesel::update(nodes)
+        .filter(node_id.eq(new_battery_level.node_id))
+        .set(battery_level.eq(level))
+        .execute(db_connection)
+        {
+            Ok(_) => (),
+            Err(e) => error!(
+                "Error while trying to update battery level for {:?} : {:?}",
+                message, e
+            ),
+        }
+}
+
+fn record_pong(
+    db_connection: &PooledConnection<ConnectionManager<SqliteConnection>>,
+    message: &InternalMessage,
+) {
+    use crate::model::node_ping::node_pings::dsl::*;
+    let received = Utc::now().naive_utc();
+    let pending_ping = node_pings
+        .filter(node_id.eq(i32::from(message.node_id)))
+        .filter(received_at.is_null())
+        .order(sent_at.desc())
+        .first::<NodePing>(db_connection)
+        .optional();
+    match pending_ping {
+        Ok(Some(ping)) => match diesel::update(node_pings.find(ping.id))
+            .set((
+                received_at.eq(received),
+                hops.eq(message.payload.parse::<i32>().ok()),
+                round_trip_ms.eq(i32::try_from((received - ping.sent_at).num_milliseconds()).ok()),
+            ))
+            .execute(db_connection)
+            {
+                Ok(_) => info!("Recorded pong from node {}", message.node_id),
+                Err(e) => error!("Update node ping failed {:?}", e),
+            },
+        Ok(None) => warn!("Ignoring pong from node {} without pending ping", message.node_id),
+        Err(e) => error!("Error while loading pending ping {:?}", e),
+    }
+}
+
+fn update_inclusion_mode(inclusion: &Inclusion, message: &InternalMessage) {
+    match message.payload.as_str() {
+        "1" => {
+            info!("Gateway started inclusion mode");
+            if inclusion.remaining().is_none() {
+                match inclusion.open(DEFAULT_INCLUSION_DURATION) {
+                    Ok(_) => (),
+                    Err(e) => error!("Error while opening inclusion {}", e),
+                }
+            }
+        }
+        "0" => {
+            info!("Gateway stopped inclusion mode");
+            inclusion.close();
+        }
+        _ => warn!("Unknown inclusion mode {}", message.payload),
+    }
+}
+
 fn send_current_time(response_sender: &Sender<String>, mut message: InternalMessage) {
     let start = SystemTime::now();
     let since_the_epoch = start
@@ -82,6 +264,30 @@ fn send_current_time(response_sender: &Sender<String>, mut message: InternalMess
     }
 }
 
+fn send_config(
+    db_connection: &PooledConnection<ConnectionManager<SqliteConnection>>,
+    response_sender: &Sender<String>,
+    default_unit_system: UnitSystem,
+    mut message: InternalMessage,
+) {
+    let node_unit_system = match dsl::nodes
+        .find(i32::from(message.node_id))
+        .first::<Node>(db_connection)
+        .optional()
+        {
+            Ok(node) => node.and_then(|node| node.unit_system),
+            Err(e) => {
+                error!("Error while loading node for {:?} : {:?}", message, e);
+                None
+            }
+        };
+    let unit_system = node_unit_system.unwrap_or(default_unit_system);
+    match response_sender.send(message.as_response(unit_system.config_payload().to_owned())) {
+        Ok(_) => (),
+        Err(_) => error!("Error while sending to node_handler"),
+    }
+}
+
 fn send_discover_response(
     db_connection: &PooledConnection<ConnectionManager<SqliteConnection>>,
     message: &InternalMessage,
@@ -112,6 +318,11 @@ fn forward_to_controller(controller_sender: &Sender<String>, message: InternalMe
     }
 }
 
+/// Whether a node not yet known to the controller may be created.
+pub fn accepts_new_node(inclusion: &Inclusion, node_id_policy: &NodeIdPolicy, node_id: u8) -> bool {
+    inclusion.is_open() && !node_id_policy.is_blocked(node_id)
+}
+
 pub fn create_node(conn: &SqliteConnection, id: i32) -> Result<Node, diesel::result::Error> {
     let new_node = Node {
         node_id: id,
@@ -123,12 +334,23 @@ pub fn create_node(conn: &SqliteConnection, id: i32) -> Result<Node, diesel::res
         auto_update: false,
         scheduled: false,
         parent_node_id: 0,
+        last_seen: Some(Utc::now().naive_utc()),
+        timeout_secs: None,
+        battery_level: None,
+        sketch_name: None,
+        sketch_version: None,
+        library_version: None,
+        is_repeater: false,
+        unit_system: None,
+        ota_window: None,
+        last_boot_at: None,
     };
 
     diesel::insert_into(dsl::nodes)
         .values(&new_node)
-        .execute(conn)
-        .map(|_| new_node)
+        .execute(conn)?;
+    diesel::delete(node_id_reservations::table.find(id)).execute(conn)?;
+    Ok(new_node)
 }
 
 pub fn update_network_topology(
@@ -143,16 +365,191 @@ pub fn update_network_topology(
         .execute(conn)
 }
 
-pub fn get_next_node_id(conn: &SqliteConnection) -> Option<u8> {
-    let existing_nodes = dsl::nodes
-        .load::<Node>(conn)
-        .expect("error while loading existing nodes");
-    let used_node_ids: Vec<u8> = existing_nodes.iter().map(|node| node.node_id()).collect();
-    for node_id in MIN_NODE_ID..=MAX_NODE_ID {
-        if used_node_ids.contains(&node_id) {
-            continue;
+pub fn update_last_seen(
+    conn: &SqliteConnection,
+    _node_id: i32,
+    seen_at: NaiveDateTime,
+) -> Result<usize, diesel::result::Error> {
+    use crate::model::node::nodes::dsl::*;
+    diesel::update(nodes)
+        .filter(node_id.eq(_node_id))
+        .set(last_seen.eq(seen_at))
+        .execute(conn)
+}
+
+pub fn get_next_node_id(
+    conn: &SqliteConnection,
+    node_id_policy: &NodeIdPolicy,
+) -> Result<Option<u8>, diesel::result::Error> {
+    let existing_nodes = dsl::nodes.load::<Node>(conn)?;
+    let reserved_node_ids = node_id_reservations::table
+        .select(node_id_reservations::node_id)
+        .load::<i32>(conn)?;
+    let recently_deleted_node_ids = match node_id_policy.reuse_after_days {
+        Some(days) => deleted_nodes::table
+            .select(deleted_nodes::node_id)
+            .filter(deleted_nodes::deleted_at.gt(Utc::now().naive_utc() - Duration::days(days)))
+            .load::<i32>(conn)?,
+        None => Vec::new(),
+    };
+    let unavailable_node_ids: Vec<u8> = existing_nodes
+        .iter()
+        .map(|node| node.node_id())
+        .chain(
+            reserved_node_ids
+                .into_iter()
+                .chain(recently_deleted_node_ids)
+                .map(|id| id as u8),
+        )
+        .collect();
+    Ok(node_id_policy.first_free(&unavailable_node_ids))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::db;
+    use crate::model::node_ping::NewNodePing;
+    use crate::model::node_ping::node_pings::dsl::*;
+
+    fn pong(payload: &str) -> InternalMessage {
+        InternalMessage::build(1, 255, 25, 0, payload).unwrap()
+    }
+
+    fn add_ping(connection: &SqliteConnection, minutes_ago: i64) {
+        diesel::insert_into(node_pings)
+            .values(&NewNodePing {
+                node_id: 1,
+                sent_at: Utc::now().naive_utc() - Duration::minutes(minutes_ago),
+            })
+            .execute(connection)
+            .unwrap();
+    }
+
+    fn pings(connection: &SqliteConnection) -> Vec<NodePing> {
+        node_pings.order(id.asc()).load::<NodePing>(connection).unwrap()
+    }
+
+    fn add_node(connection: &SqliteConnection) {
+        diesel::insert_into(crate::model::node::nodes::table)
+            .values(&Node {
+                node_id: 1,
+                ..Node::default()
+            })
+            .execute(connection)
+            .unwrap();
+    }
+
+    fn message(sub_type: InternalType, payload: &str) -> InternalMessage {
+        InternalMessage::build(1, 255, sub_type as u8, 0, payload).unwrap()
+    }
+
+    fn battery_level_of_node(connection: &SqliteConnection) -> Option<i32> {
+        crate::model::node::nodes::table
+            .find(1)
+            .first::<Node>(connection)
+            .unwrap()
+            .battery_level
+    }
+
+    #[test]
+    fn battery_level_is_stored_with_its_history() {
+        use crate::model::battery_level::battery_levels::dsl::level;
+        let connection = db::test::pool().get().unwrap();
+        add_node(&connection);
+        update_battery_level(&connection, &message(InternalType::BatteryLevel, "87"));
+        update_battery_level(&connection, &message(InternalType::BatteryLevel, " 42\n"));
+        assert_eq!(Some(42), battery_level_of_node(&connection));
+        let levels = battery_levels.select(level).load::<i32>(&connection).unwrap();
+        assert_eq!(vec![87, 42], levels);
+    }
+
+    #[test]
+    fn battery_level_outside_percentage_is_ignored() {
+        let connection = db::test::pool().get().unwrap();
+        add_node(&connection);
+        update_battery_level(&connection, &message(InternalType::BatteryLevel, "50"));
+        for payload in &["abc", "", "101", "-1", "300"] {
+            update_battery_level(&connection, &message(InternalType::BatteryLevel, payload));
         }
-        return Some(node_id);
+        assert_eq!(Some(50), battery_level_of_node(&connection));
+        assert_eq!(Ok(1), battery_levels.count().get_result::<i64>(&connection));
+    }
+
+    fn sketch_of_node(connection: &SqliteConnection) -> (String, Option<String>, Option<String>) {
+        let node = crate::model::node::nodes::table
+            .find(1)
+            .first::<Node>(connection)
+            .unwrap();
+        (node.node_name, node.sketch_name, node.sketch_version)
+    }
+
+    #[test]
+    fn sketch_name_and_version_are_stored() {
+        let connection = db::test::pool().get().unwrap();
+        add_node(&connection);
+        diesel::update(crate::model::node::nodes::table.find(1))
+            .set(dsl::node_name.eq("New Node"))
+            .execute(&connection)
+            .unwrap();
+        update_sketch_name(&connection, message(InternalType::SketchName, "Garage door"));
+        update_sketch_version(&connection, &message(InternalType::SketchVersion, "1.2"));
+        update_sketch_name(&connection, message(InternalType::SketchName, "Gate"));
+        assert_eq!(
+            (
+                "Garage door".to_owned(),
+                Some("Gate".to_owned()),
+                Some("1.2".to_owned())
+            ),
+            sketch_of_node(&connection)
+        );
+    }
+
+    #[test]
+    fn empty_sketch_info_is_ignored_and_overlong_is_cut() {
+        let connection = db::test::pool().get().unwrap();
+        add_node(&connection);
+        update_sketch_name(&connection, message(InternalType::SketchName, "Garage door"));
+        update_sketch_version(&connection, &message(InternalType::SketchVersion, "1.2"));
+        update_sketch_name(&connection, message(InternalType::SketchName, " "));
+        update_sketch_version(&connection, &message(InternalType::SketchVersion, ""));
+        let (_, name, version) = sketch_of_node(&connection);
+        assert_eq!(Some("Garage door".to_owned()), name);
+        assert_eq!(Some("1.2".to_owned()), version);
+        update_sketch_name(&connection, message(InternalType::SketchName, &"a".repeat(40)));
+        assert_eq!(Some("a".repeat(25)), sketch_of_node(&connection).1);
+    }
+
+    #[test]
+    fn pong_is_recorded_on_newest_pending_ping_with_its_hops() {
+        let connection = db::test::pool().get().unwrap();
+        add_ping(&connection, 2);
+        add_ping(&connection, 1);
+        record_pong(&connection, &pong("3"));
+        let pings = pings(&connection);
+        assert_eq!(None, pings[0].received_at);
+        assert!(pings[1].received_at.is_some());
+        assert_eq!(Some(3), pings[1].hops);
+        assert!(pings[1].round_trip_ms.unwrap() >= 60_000);
+    }
+
+    #[test]
+    fn pong_without_number_of_hops_is_still_recorded() {
+        let connection = db::test::pool().get().unwrap();
+        add_ping(&connection, 0);
+        record_pong(&connection, &pong("x"));
+        let pings = pings(&connection);
+        assert!(pings[0].received_at.is_some());
+        assert_eq!(None, pings[0].hops);
+    }
+
+    #[test]
+    fn pong_without_pending_ping_is_ignored() {
+        let connection = db::test::pool().get().unwrap();
+        add_ping(&connection, 1);
+        record_pong(&connection, &pong("1"));
+        let answered = pings(&connection);
+        record_pong(&connection, &pong("2"));
+        assert_eq!(answered, pings(&connection));
     }
-    None
 }
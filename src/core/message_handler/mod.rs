@@ -1,4 +1,5 @@
 pub mod internal;
 pub mod presentation;
+pub mod req;
 pub mod set;
 pub mod stream;
@@ -4,21 +4,42 @@ use diesel::r2d2::ConnectionManager;
 use r2d2::*;
 
 use crate::channel::{Receiver, Sender};
-use crate::core::message::presentation::PresentationMessage;
+use crate::core::inclusion::Inclusion;
+use crate::core::node_id_policy::NodeIdPolicy;
+use crate::core::message::presentation::{PresentationMessage, PresentationType};
 use crate::model::node::Node;
 use crate::model::node::nodes;
 use crate::model::sensor::Sensor;
 use crate::model::sensor::sensors::dsl::*;
 
+use super::internal::accepts_new_node;
+
 pub fn handle(
     receiver: &Receiver<PresentationMessage>,
     sender: &Sender<String>,
     db_connection: PooledConnection<ConnectionManager<SqliteConnection>>,
     new_sensor_sender: Sender<(String, Sensor)>,
+    inclusion: &Inclusion,
+    node_id_policy: &NodeIdPolicy,
 ) {
     loop {
         if let Ok(presentation_message) = receiver.recv() {
-            create_or_update_sensor(&db_connection, &presentation_message, &new_sensor_sender);
+            if presentation_message.sub_type.is_node() {
+                update_node_capabilities(
+                    &db_connection,
+                    &presentation_message,
+                    inclusion,
+                    node_id_policy,
+                );
+            } else {
+                create_or_update_sensor(
+                    &db_connection,
+                    &presentation_message,
+                    &new_sensor_sender,
+                    inclusion,
+                    node_id_policy,
+                );
+            }
             match sender.send(presentation_message.to_string()) {
                 Ok(_) => (),
                 Err(_) => error!("Error while forwarding presentation message"),
@@ -27,11 +48,54 @@ pub fn handle(
     }
 }
 
+pub fn update_node_capabilities(
+    conn: &SqliteConnection,
+    presentation_message: &PresentationMessage,
+    inclusion: &Inclusion,
+    node_id_policy: &NodeIdPolicy,
+) {
+    use crate::model::node::nodes::dsl::*;
+    let presented_node_id = i32::from(presentation_message.node_id);
+    match nodes.find(presented_node_id).first::<Node>(conn).optional() {
+        Ok(Some(_)) => (),
+        Ok(None) if !accepts_new_node(inclusion, node_id_policy, presentation_message.node_id) => {
+            warn!(
+                "Ignoring presentation of unknown node {} as inclusion mode is not active or its id is blocked",
+                presented_node_id
+            );
+            return;
+        }
+        Ok(None) => match super::internal::create_node(conn, presented_node_id) {
+            Ok(_) => info!("Created new node for {:?}", presentation_message),
+            Err(e) => {
+                error!("Error while creating new node for {}, {:?}", presented_node_id, e);
+                return;
+            }
+        },
+        Err(e) => {
+            error!("Error while checking for existing node for {}, {:?}", presented_node_id, e);
+            return;
+        }
+    }
+    match diesel::update(nodes)
+        .filter(node_id.eq(presented_node_id))
+        .set((
+            library_version.eq(&presentation_message.payload),
+            is_repeater.eq(presentation_message.sub_type == PresentationType::ArduinoRepeaterNode),
+        ))
+        .execute(conn)
+        {
+            Ok(_) => info!("Updated capabilities of node {}", presented_node_id),
+            Err(e) => error!("Update node capabilities failed {:?}", e),
+        }
+}
 
 pub fn create_or_update_sensor(
     conn: &SqliteConnection,
     presentation_message: &PresentationMessage,
     new_sensor_sender: &Sender<(String, Sensor)>,
+    inclusion: &Inclusion,
+    node_id_policy: &NodeIdPolicy,
 ) {
     let sensor_message = Sensor {
         node_id: i32::from(presentation_message.node_id),
@@ -45,6 +109,14 @@ pub fn create_or_update_sensor(
         .first::<Node>(conn)
         {
             Ok(node) => create_or_update_child_sensor(&conn, node, sensor_message, new_sensor_sender),
+            Err(diesel::result::Error::NotFound)
+                if !accepts_new_node(inclusion, node_id_policy, presentation_message.node_id) =>
+            {
+                warn!(
+                    "Ignoring presentation of unknown node {} as inclusion mode is not active or its id is blocked",
+                    sensor_message.node_id
+                )
+            }
             Err(diesel::result::Error::NotFound) => {
                 info!(
                     "Node doesn't exist for {:?}, Creating new node",
@@ -0,0 +1,60 @@
+use diesel;
+use diesel::prelude::*;
+use diesel::r2d2::ConnectionManager;
+use r2d2::*;
+
+use crate::channel::{Receiver, Sender};
+use crate::core::message::req::ReqMessage;
+use crate::model::last_value::LastValue;
+use crate::model::last_value::last_values::dsl::last_values;
+
+pub fn handle(
+    receiver: &Receiver<ReqMessage>,
+    response_sender: &Sender<String>,
+    controller_forward_sender: &Sender<String>,
+    db_connection: PooledConnection<ConnectionManager<SqliteConnection>>,
+) {
+    loop {
+        if let Ok(req_message) = receiver.recv() {
+            match find_last_value(&db_connection, &req_message) {
+                Some(last_value) => send_last_value(response_sender, req_message, last_value),
+                None => forward_to_controller(controller_forward_sender, req_message),
+            }
+        }
+    }
+}
+
+fn find_last_value(conn: &SqliteConnection, req_message: &ReqMessage) -> Option<LastValue> {
+    match last_values
+        .find((
+            i32::from(req_message.node_id),
+            i32::from(req_message.child_sensor_id),
+            req_message.sub_type,
+        ))
+        .first::<LastValue>(conn)
+        .optional()
+        {
+            Ok(last_value) => last_value,
+            Err(e) => {
+                error!("Error while looking up last value for {:?} : {:?}", req_message, e);
+                None
+            }
+        }
+}
+
+fn send_last_value(response_sender: &Sender<String>, req_message: ReqMessage, last_value: LastValue) {
+    match response_sender.send(req_message.as_response(last_value.value).to_string()) {
+        Ok(_) => (),
+        Err(error) => error!("Error while sending last value to gateway {:?}", error),
+    }
+}
+
+fn forward_to_controller(controller_sender: &Sender<String>, req_message: ReqMessage) {
+    match controller_sender.send(req_message.to_string()) {
+        Ok(_) => (),
+        Err(error) => error!(
+            "Error while forwarding req message to controller {:?}",
+            error
+        ),
+    }
+}
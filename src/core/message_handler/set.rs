@@ -1,8 +1,18 @@
 use std::thread;
 use std::thread::JoinHandle;
 
+use chrono::Utc;
+use diesel;
+use diesel::prelude::*;
+use diesel::r2d2::ConnectionManager;
+use r2d2::*;
+
 use crate::channel::{Receiver, Sender};
 use crate::core::message::set::*;
+use crate::model::last_value::LastValue;
+use crate::model::last_value::last_values::dsl::last_values;
+use crate::model::sensor_value::NewSensorValue;
+use crate::model::sensor_value::sensor_values::dsl::sensor_values;
 
 pub fn handle_from_controller(
     set_message_receiver: Receiver<SetMessage>,
@@ -24,10 +34,13 @@ pub fn handle_from_gateway(
     receiver: Receiver<SetMessage>,
     property_notify_sender: Sender<SetMessage>,
     controller_sender: Sender<String>,
+    db_connection: PooledConnection<ConnectionManager<SqliteConnection>>,
 ) -> JoinHandle<()> {
     thread::spawn(move || {
         loop {
             if let Ok(set_message) = receiver.recv() {
+                update_last_value(&db_connection, &set_message);
+                store_value(&db_connection, &set_message);
                 match controller_sender.send(set_message.to_string()) {
                     Ok(_) => (),
                     Err(error) => error!("Error while sending to controller_sender {:?}", error),
@@ -42,3 +55,38 @@ pub fn handle_from_gateway(
         }
     })
 }
+
+fn update_last_value(conn: &SqliteConnection, set_message: &SetMessage) {
+    let last_value = LastValue {
+        node_id: i32::from(set_message.node_id),
+        child_sensor_id: i32::from(set_message.child_sensor_id),
+        set_type: set_message.value.set_type,
+        value: set_message.value.value.clone(),
+        updated_at: Utc::now().naive_utc(),
+    };
+    match diesel::replace_into(last_values)
+        .values(&last_value)
+        .execute(conn)
+        {
+            Ok(_) => (),
+            Err(e) => error!("Error while storing last value {:?} : {:?}", last_value, e),
+        }
+}
+
+fn store_value(conn: &SqliteConnection, set_message: &SetMessage) {
+    let sensor_value = NewSensorValue {
+        node_id: i32::from(set_message.node_id),
+        child_sensor_id: i32::from(set_message.child_sensor_id),
+        set_type: set_message.value.set_type,
+        value: set_message.value.value.clone(),
+        numeric_value: set_message.value.numeric_value(),
+        received_at: Utc::now().naive_utc(),
+    };
+    match diesel::insert_into(sensor_values)
+        .values(&sensor_value)
+        .execute(conn)
+        {
+            Ok(_) => (),
+            Err(e) => error!("Error while storing sensor value {:?} : {:?}", sensor_value, e),
+        }
+}
@@ -1,23 +1,36 @@
+use std::thread;
+use std::thread::JoinHandle;
+
+use chrono::{Duration, Local, NaiveDateTime, Utc};
 use diesel;
 use diesel::prelude::*;
 use diesel::r2d2::ConnectionManager;
 use r2d2::*;
 
 use crate::channel::{Receiver, Sender};
+use crate::core::maintenance_window::{self, MaintenanceWindow};
 use crate::core::message::stream::*;
 use crate::model::firmware::Firmware;
 use crate::model::firmware::firmwares::dsl::firmwares;
+use crate::model::firmware_history::{FirmwareHistory, NewFirmwareHistory};
 use crate::model::node::Node;
 use crate::model::node::nodes::dsl::*;
+use crate::model::ota_session::{NewOtaSession, OtaSession, OtaStatus, QueuedOta};
+
+/// Sessions without a block requested for this long don't hold an ota slot anymore.
+const STALE_SESSION_SECS: i64 = 600;
+const QUEUE_CHECK_INTERVAL_SECS: u64 = 60;
 
 pub fn handle(
     ota_receiver: &Receiver<StreamMessage>,
     sender: &Sender<String>,
     db_connection: PooledConnection<ConnectionManager<SqliteConnection>>,
+    max_concurrent_ota: Option<usize>,
+    ota_windows: Vec<MaintenanceWindow>,
 ) {
     loop {
         if let Ok(stream_request) = ota_receiver.recv() {
-            send_response(sender, stream_request, &db_connection)
+            send_response(sender, stream_request, &db_connection, max_concurrent_ota, &ota_windows)
         }
     }
 }
@@ -26,25 +39,42 @@ fn send_response(
     stream_response_sender: &Sender<String>,
     mut stream: StreamMessage,
     db_connection: &SqliteConnection,
+    max_concurrent_ota: Option<usize>,
+    ota_windows: &[MaintenanceWindow],
 ) {
     if let Ok(node) = nodes
         .find(i32::from(stream.node_id))
         .first::<Node>(db_connection)
         .optional()
     {
+        let node_ota_window = node.as_ref().and_then(|node| node.ota_window.clone());
         if let Some((_type, version)) = response_fw_type_version(stream, node, db_connection) {
             match firmwares
                 .find((i32::from(_type), i32::from(version)))
                 .first::<Firmware>(&*db_connection) {
                 Ok(firmware) => {
                     debug!("Request {:?}", stream);
-                    stream.response(&firmware);
+                    let transfer_done = match stream.payload {
+                        StreamPayload::FwRequest(ref request) => request.blocks == 0,
+                        _ => false,
+                    };
+                    if !in_maintenance_window(&stream, &firmware, node_ota_window.as_ref(), ota_windows) {
+                        stream.keep_current_firmware();
+                    } else if admit_ota(db_connection, &stream, &firmware, max_concurrent_ota) {
+                        track_ota_session(db_connection, &stream, &firmware);
+                        stream.response(&firmware);
+                    } else {
+                        stream.keep_current_firmware();
+                    }
                     debug!("Response {:?}", stream);
                     let response = stream.to_string();
                     match stream_response_sender.send(response) {
                         Ok(_) => (),
                         Err(_) => error!("Error sending to stream response sender"),
                     }
+                    if transfer_done {
+                        wake_next_queued(db_connection, stream_response_sender);
+                    }
                 }
                 Err(_message) => {
                     warn!(
@@ -71,10 +101,20 @@ fn response_fw_type_version(
 
             match node {
                 Some(_node) => {
+                    let booted_at = Utc::now().naive_utc();
+                    match confirm_ota_outcome(connection, &_node, request, booted_at) {
+                        Ok(_) => (),
+                        Err(e) => error!("Error while confirming ota outcome of node {} {:?}", _node.node_id, e),
+                    }
+                    match record_firmware_history(connection, _node.node_id, request) {
+                        Ok(_) => (),
+                        Err(e) => error!("Error while recording firmware history of node {} {:?}", _node.node_id, e),
+                    }
                     match diesel::update(nodes.filter(node_id.eq(_node.node_id)))
                         .set((
                             firmware_type.eq(i32::from(request.firmware_type)),
                             firmware_version.eq(i32::from(request.firmware_version)),
+                            last_boot_at.eq(booted_at),
                         ))
                         .execute(connection)
                         {
@@ -95,3 +135,388 @@ fn response_fw_type_version(
         _ => None,
     }
 }
+
+/// Whether a node may be offered a firmware other than the one it runs at this time of day,
+/// the node's own windows take precedence over the installation ones.
+fn in_maintenance_window(
+    stream: &StreamMessage,
+    firmware: &Firmware,
+    node_ota_window: Option<&String>,
+    ota_windows: &[MaintenanceWindow],
+) -> bool {
+    let request = match stream.payload {
+        StreamPayload::FwConfigRequest(request) => request,
+        _ => return true,
+    };
+    if runs_firmware(request, firmware) {
+        return true;
+    }
+    let windows = match node_ota_window.map(|window| maintenance_window::parse_windows(window)) {
+        Some(Ok(node_windows)) => node_windows,
+        Some(Err(e)) => {
+            warn!("Ignoring ota window of node {}, {}", stream.node_id, e);
+            ota_windows.to_vec()
+        }
+        None => ota_windows.to_vec(),
+    };
+    let open = maintenance_window::is_open(&windows, Local::now().time());
+    if !open {
+        info!("Node {} is outside its maintenance window, keeping its firmware", stream.node_id);
+    }
+    open
+}
+
+fn runs_firmware(request: FwConfigRequestMessage, firmware: &Firmware) -> bool {
+    i32::from(request.firmware_type) == firmware.firmware_type
+        && i32::from(request.firmware_version) == firmware.firmware_version
+        && i32::from(request.crc) == firmware.crc
+}
+
+/// Whether the node may start its update now, nodes beyond `max_concurrent_ota`
+/// are queued and served in order once slots free up. Nodes are not admitted
+/// when the slots can't be checked, so the limit holds.
+fn admit_ota(
+    connection: &SqliteConnection,
+    stream: &StreamMessage,
+    firmware: &Firmware,
+    max_concurrent_ota: Option<usize>,
+) -> bool {
+    let request = match stream.payload {
+        StreamPayload::FwConfigRequest(request) => request,
+        _ => return true,
+    };
+    let max_concurrent_ota = match max_concurrent_ota {
+        Some(max_concurrent_ota) => max_concurrent_ota,
+        None => return true,
+    };
+    let queued_node_id = i32::from(stream.node_id);
+    let result = if runs_firmware(request, firmware) {
+        dequeue(connection, queued_node_id).map(|_| true)
+    } else {
+        active_sessions(connection).and_then(|active| {
+            let queue = queued_node_ids(connection)?;
+            if may_start(active, max_concurrent_ota, &queue, queued_node_id) {
+                dequeue(connection, queued_node_id).map(|_| true)
+            } else {
+                enqueue(connection, queued_node_id, firmware).map(|_| false)
+            }
+        })
+    };
+    match result {
+        Ok(admitted) => admitted,
+        Err(e) => {
+            error!("Error while checking ota slots for node {} {:?}", queued_node_id, e);
+            false
+        }
+    }
+}
+
+/// Queued nodes take the free slots first, in the order they were queued.
+pub fn may_start(active: usize, max_concurrent_ota: usize, queue: &[i32], requesting_node_id: i32) -> bool {
+    let free_slots = max_concurrent_ota.saturating_sub(active);
+    match queue.iter().position(|queued_node_id| *queued_node_id == requesting_node_id) {
+        Some(position) => position < free_slots,
+        None => queue.len() < free_slots,
+    }
+}
+
+fn active_sessions(connection: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    use crate::model::ota_session::ota_sessions::dsl::*;
+    let cutoff = Utc::now().naive_utc() - Duration::seconds(STALE_SESSION_SECS);
+    ota_sessions
+        .filter(status.eq(OtaStatus::InProgress))
+        .filter(last_block_at.gt(cutoff).or(last_block_at.is_null().and(started_at.gt(cutoff))))
+        .count()
+        .get_result::<i64>(connection)
+        .map(|count| count as usize)
+}
+
+fn queued_node_ids(connection: &SqliteConnection) -> Result<Vec<i32>, diesel::result::Error> {
+    use crate::model::ota_session::ota_queue::dsl::*;
+    ota_queue
+        .select(node_id)
+        .order(queued_at.asc())
+        .load::<i32>(connection)
+}
+
+fn enqueue(connection: &SqliteConnection, queued_node_id: i32, firmware: &Firmware) -> Result<(), diesel::result::Error> {
+    use crate::model::ota_session::ota_queue::dsl::*;
+    let already_queued = ota_queue
+        .find(queued_node_id)
+        .first::<QueuedOta>(connection)
+        .optional()?;
+    match already_queued {
+        Some(_) => diesel::update(ota_queue.find(queued_node_id))
+            .set((
+                firmware_type.eq(firmware.firmware_type),
+                firmware_version.eq(firmware.firmware_version),
+            ))
+            .execute(connection)?,
+        None => {
+            info!("No free ota slot, queueing node {}", queued_node_id);
+            diesel::insert_into(ota_queue)
+                .values(&QueuedOta {
+                    node_id: queued_node_id,
+                    firmware_type: firmware.firmware_type,
+                    firmware_version: firmware.firmware_version,
+                    queued_at: Utc::now().naive_utc(),
+                })
+                .execute(connection)?
+        }
+    };
+    Ok(())
+}
+
+fn dequeue(connection: &SqliteConnection, queued_node_id: i32) -> Result<(), diesel::result::Error> {
+    use crate::model::ota_session::ota_queue::dsl::*;
+    diesel::delete(ota_queue.find(queued_node_id))
+        .execute(connection)
+        .map(|_| ())
+}
+
+/// Reboots the first queued node once a transfer is done, so it asks for its firmware again.
+fn wake_next_queued(connection: &SqliteConnection, stream_response_sender: &Sender<String>) {
+    match queued_node_ids(connection) {
+        Ok(queue) => wake_queued(&queue, 1, stream_response_sender),
+        Err(e) => error!("Error while loading ota queue {:?}", e),
+    }
+}
+
+/// Periodically wakes queued nodes for the slots freed by stalled sessions,
+/// which never finish their transfer.
+pub fn monitor_queue(
+    pool: Pool<ConnectionManager<SqliteConnection>>,
+    stream_response_sender: Sender<String>,
+    max_concurrent_ota: usize,
+) -> JoinHandle<()> {
+    thread::spawn(move || loop {
+        match pool.get() {
+            Ok(connection) => wake_for_stale_sessions(&connection, &stream_response_sender, max_concurrent_ota),
+            Err(e) => error!("Error while getting connection for ota queue {:?}", e),
+        }
+        thread::sleep(std::time::Duration::from_secs(QUEUE_CHECK_INTERVAL_SECS));
+    })
+}
+
+fn wake_for_stale_sessions(
+    connection: &SqliteConnection,
+    stream_response_sender: &Sender<String>,
+    max_concurrent_ota: usize,
+) {
+    let result = active_sessions(connection).and_then(|active| {
+        queued_node_ids(connection).map(|queue| (max_concurrent_ota.saturating_sub(active), queue))
+    });
+    match result {
+        Ok((free_slots, queue)) => wake_queued(&queue, free_slots, stream_response_sender),
+        Err(e) => error!("Error while checking ota queue {:?}", e),
+    }
+}
+
+fn wake_queued(queue: &[i32], count: usize, stream_response_sender: &Sender<String>) {
+    for next_node_id in queue.iter().take(count) {
+        info!("Ota slot freed, rebooting queued node {}", next_node_id);
+        match stream_response_sender.send(format!("{};255;3;0;13;0\n", next_node_id)) {
+            Ok(_) => (),
+            Err(_) => error!("Error while sending reboot to queued node"),
+        }
+    }
+}
+
+fn track_ota_session(connection: &SqliteConnection, stream: &StreamMessage, firmware: &Firmware) {
+    let result = match stream.payload {
+        StreamPayload::FwConfigRequest(request) => {
+            start_ota_session(connection, i32::from(stream.node_id), request, firmware)
+        }
+        StreamPayload::FwRequest(request) => {
+            record_block_served(connection, i32::from(stream.node_id), request.blocks, firmware)
+        }
+        _ => Ok(()),
+    };
+    match result {
+        Ok(_) => (),
+        Err(e) => error!("Error while tracking ota session of node {} {:?}", stream.node_id, e),
+    }
+}
+
+/// A node asks for its firmware config after every reboot, so the firmware it advertises
+/// tells whether a transferred update was installed. Transfers still in progress were aborted.
+fn confirm_ota_outcome(
+    connection: &SqliteConnection,
+    node: &Node,
+    request: FwConfigRequestMessage,
+    booted_at: NaiveDateTime,
+) -> Result<(), diesel::result::Error> {
+    use crate::model::ota_session::ota_sessions::dsl::*;
+    let advertised_crc = firmwares
+        .find((i32::from(request.firmware_type), i32::from(request.firmware_version)))
+        .first::<Firmware>(connection)
+        .optional()?
+        .map(|firmware| firmware.crc);
+    let crc_matches = match advertised_crc {
+        Some(crc) => crc == i32::from(request.crc),
+        None => true,
+    };
+    let runs = |_type: i32, version: i32| {
+        i32::from(request.firmware_type) == _type && i32::from(request.firmware_version) == version && crc_matches
+    };
+    let unfinished_sessions = ota_sessions
+        .filter(node_id.eq(node.node_id))
+        .filter(status.eq(OtaStatus::InProgress).or(status.eq(OtaStatus::Transferred)))
+        .load::<OtaSession>(connection)?;
+    for session in unfinished_sessions {
+        let outcome = if session.status == OtaStatus::Transferred
+            && runs(session.firmware_type, session.firmware_version)
+        {
+            info!("Ota session of node {} completed", node.node_id);
+            OtaStatus::Completed
+        } else {
+            warn!(
+                "Ota session of node {} failed, node runs type {} version {}",
+                node.node_id, request.firmware_type, request.firmware_version
+            );
+            OtaStatus::Failed
+        };
+        diesel::update(ota_sessions.find(session.id))
+            .set((status.eq(outcome), finished_at.eq(booted_at)))
+            .execute(connection)?;
+    }
+    if node.scheduled && runs(node.desired_firmware_type, node.desired_firmware_version) {
+        clear_scheduled_update(connection, node.node_id)?;
+    }
+    Ok(())
+}
+
+fn clear_scheduled_update(
+    connection: &SqliteConnection,
+    updated_node_id: i32,
+) -> Result<(), diesel::result::Error> {
+    info!("Node {} runs its desired firmware, clearing scheduled update", updated_node_id);
+    diesel::update(nodes.filter(node_id.eq(updated_node_id)))
+        .set(scheduled.eq(false))
+        .execute(connection)
+        .map(|_| ())
+}
+
+/// Adds the advertised firmware to the node history when it differs from the last one seen.
+fn record_firmware_history(
+    connection: &SqliteConnection,
+    history_node_id: i32,
+    request: FwConfigRequestMessage,
+) -> Result<(), diesel::result::Error> {
+    use crate::model::firmware_history::firmware_history::dsl::*;
+    let advertised = (i32::from(request.firmware_type), i32::from(request.firmware_version));
+    let last_seen_firmware = firmware_history
+        .filter(node_id.eq(history_node_id))
+        .order((observed_at.desc(), id.desc()))
+        .first::<FirmwareHistory>(connection)
+        .optional()?
+        .map(|entry| (entry.firmware_type, entry.firmware_version));
+    if last_seen_firmware == Some(advertised) {
+        return Ok(());
+    }
+    diesel::insert_into(firmware_history)
+        .values(&NewFirmwareHistory {
+            node_id: history_node_id,
+            firmware_type: advertised.0,
+            firmware_version: advertised.1,
+            observed_at: Utc::now().naive_utc(),
+        })
+        .execute(connection)
+        .map(|_| ())
+}
+
+/// A new session starts when a node is sent a firmware other than the one it runs.
+fn start_ota_session(
+    connection: &SqliteConnection,
+    session_node_id: i32,
+    request: FwConfigRequestMessage,
+    firmware: &Firmware,
+) -> Result<(), diesel::result::Error> {
+    use crate::model::ota_session::ota_sessions::dsl::*;
+    if runs_firmware(request, firmware) {
+        return Ok(());
+    }
+    info!(
+        "Starting ota session of node {} to type {} version {}",
+        session_node_id, firmware.firmware_type, firmware.firmware_version
+    );
+    diesel::insert_into(ota_sessions)
+        .values(&NewOtaSession {
+            node_id: session_node_id,
+            firmware_type: firmware.firmware_type,
+            firmware_version: firmware.firmware_version,
+            total_blocks: firmware.blocks,
+            started_at: Utc::now().naive_utc(),
+            status: OtaStatus::InProgress,
+        })
+        .execute(connection)
+        .map(|_| ())
+}
+
+fn record_block_served(
+    connection: &SqliteConnection,
+    session_node_id: i32,
+    block: u16,
+    firmware: &Firmware,
+) -> Result<(), diesel::result::Error> {
+    use crate::model::ota_session::ota_sessions::dsl::*;
+    let block = i32::from(block);
+    let now = Utc::now().naive_utc();
+    let find_session = || {
+        ota_sessions
+            .filter(node_id.eq(session_node_id))
+            .filter(firmware_type.eq(firmware.firmware_type))
+            .filter(firmware_version.eq(firmware.firmware_version))
+            .filter(status.eq(OtaStatus::InProgress))
+            .order(id.desc())
+            .first::<OtaSession>(connection)
+            .optional()
+    };
+    let session = match find_session()? {
+        Some(session) => session,
+        None => {
+            diesel::insert_into(ota_sessions)
+                .values(&NewOtaSession {
+                    node_id: session_node_id,
+                    firmware_type: firmware.firmware_type,
+                    firmware_version: firmware.firmware_version,
+                    total_blocks: firmware.blocks,
+                    started_at: now,
+                    status: OtaStatus::InProgress,
+                })
+                .execute(connection)?;
+            find_session()?.ok_or(diesel::result::Error::NotFound)?
+        }
+    };
+    let session_status = if block == 0 {
+        info!("Ota session of node {} transferred all blocks", session_node_id);
+        OtaStatus::Transferred
+    } else {
+        OtaStatus::InProgress
+    };
+    diesel::update(ota_sessions.find(session.id))
+        .set((
+            blocks_requested.eq(session.blocks_requested + 1),
+            highest_block.eq(session.highest_block.map_or(block, |highest| highest.max(block))),
+            lowest_block.eq(session.lowest_block.map_or(block, |lowest| lowest.min(block))),
+            last_block_at.eq(now),
+            status.eq(session_status),
+        ))
+        .execute(connection)
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn queued_nodes_take_free_slots_first() {
+        assert!(may_start(0, 2, &[], 1));
+        assert!(!may_start(2, 2, &[], 1));
+        assert!(!may_start(1, 2, &[3], 1));
+        assert!(may_start(1, 2, &[3], 3));
+        assert!(may_start(0, 2, &[3, 1], 1));
+        assert!(!may_start(1, 2, &[3, 1], 1));
+    }
+}
@@ -1,5 +1,10 @@
+pub mod campaign;
 pub mod connection;
+pub mod inclusion;
 pub mod interceptor;
+pub mod maintenance_window;
 pub mod message;
 pub mod message_handler;
+pub mod node_id_policy;
+pub mod rollback;
 pub mod server;
@@ -0,0 +1,89 @@
+use std::ops::RangeInclusive;
+
+pub const MIN_NODE_ID: u8 = 1;
+pub const MAX_NODE_ID: u8 = 254;
+
+/// Rules followed when handing out node ids to nodes asking for one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NodeIdPolicy {
+    /// Ids kept for nodes with a hard coded id, never handed out automatically.
+    pub reserved: Vec<RangeInclusive<u8>>,
+    /// Ids that are never handed out and never accepted from new nodes.
+    pub blocked: Vec<RangeInclusive<u8>>,
+    /// Ids of deleted nodes are not handed out again before this many days.
+    pub reuse_after_days: Option<i64>,
+}
+
+impl NodeIdPolicy {
+    pub fn is_reserved(&self, node_id: u8) -> bool {
+        self.reserved.iter().any(|range| range.contains(&node_id))
+    }
+
+    pub fn is_blocked(&self, node_id: u8) -> bool {
+        self.blocked.iter().any(|range| range.contains(&node_id))
+    }
+
+    /// First id that can be handed out, skipping the given unavailable ids.
+    pub fn first_free(&self, unavailable: &[u8]) -> Option<u8> {
+        (MIN_NODE_ID..=MAX_NODE_ID).find(|node_id| {
+            !unavailable.contains(node_id) && !self.is_reserved(*node_id) && !self.is_blocked(*node_id)
+        })
+    }
+}
+
+/// Parses id ranges like "200-220,230".
+pub fn parse_ranges(value: &str) -> Result<Vec<RangeInclusive<u8>>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut bounds = part.splitn(2, '-').map(str::trim);
+            let start = parse_node_id(bounds.next().unwrap_or(""))?;
+            let end = match bounds.next() {
+                Some(end) => parse_node_id(end)?,
+                None => start,
+            };
+            if start > end {
+                return Err(format!("invalid node id range {}", part));
+            }
+            Ok(start..=end)
+        })
+        .collect()
+}
+
+fn parse_node_id(value: &str) -> Result<u8, String> {
+    match value.parse::<u8>() {
+        Ok(node_id) if (MIN_NODE_ID..=MAX_NODE_ID).contains(&node_id) => Ok(node_id),
+        _ => Err(format!(
+            "invalid node id {}, should be between {} and {}",
+            value, MIN_NODE_ID, MAX_NODE_ID
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_node_id_ranges() {
+        assert_eq!(Ok(vec![200..=220, 230..=230]), parse_ranges("200-220, 230"));
+        assert_eq!(Ok(vec![]), parse_ranges(""));
+        assert!(parse_ranges("220-200").is_err());
+        assert!(parse_ranges("0-10").is_err());
+        assert!(parse_ranges("abc").is_err());
+    }
+
+    #[test]
+    fn first_free_skips_reserved_and_blocked_ids() {
+        let policy = NodeIdPolicy {
+            reserved: vec![1..=2],
+            blocked: vec![4..=4],
+            reuse_after_days: None,
+        };
+        assert_eq!(Some(3), policy.first_free(&[]));
+        assert_eq!(Some(5), policy.first_free(&[3]));
+        assert_eq!(None, NodeIdPolicy::default().first_free(&(1..=254).collect::<Vec<u8>>()));
+    }
+}
@@ -0,0 +1,198 @@
+use std::thread;
+use std::thread::JoinHandle;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+
+use crate::model::firmware::Firmware;
+use crate::model::firmware_history::{previous_good, FirmwareHistory};
+use crate::model::node::Node;
+use crate::model::ota_session::OtaStatus;
+
+const CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Schedules the previous good firmware of the node and marks the current one as bad,
+/// `None` when there is no firmware to roll back to.
+pub fn rollback(connection: &SqliteConnection, node: &Node) -> Result<Option<FirmwareHistory>, diesel::result::Error> {
+    use crate::model::firmware_history::firmware_history::dsl::*;
+    let history = firmware_history
+        .filter(node_id.eq(node.node_id))
+        .order((observed_at.desc(), id.desc()))
+        .load::<FirmwareHistory>(connection)?;
+    let current = (node.firmware_type, node.firmware_version);
+    let target = match previous_good(&history, current) {
+        Some(target) => target.clone(),
+        None => return Ok(None),
+    };
+    if crate::model::firmware::firmwares::table
+        .find((target.firmware_type, target.firmware_version))
+        .first::<Firmware>(connection)
+        .optional()?
+        .is_none()
+    {
+        warn!(
+            "Can't roll back node {} to type {} version {}, firmware is not present",
+            node.node_id, target.firmware_type, target.firmware_version
+        );
+        return Ok(None);
+    }
+    connection.transaction(|| {
+        mark_current_firmware_bad(connection, node)?;
+        crate::core::campaign::schedule_update(
+            connection,
+            &[node.node_id],
+            target.firmware_type,
+            target.firmware_version,
+        )?;
+        info!(
+            "Rolling back node {} from type {} version {} to version {}",
+            node.node_id, node.firmware_type, node.firmware_version, target.firmware_version
+        );
+        Ok(Some(target))
+    })
+}
+
+fn mark_current_firmware_bad(connection: &SqliteConnection, node: &Node) -> Result<usize, diesel::result::Error> {
+    use crate::model::firmware_history::firmware_history::dsl::*;
+    diesel::update(firmware_history)
+        .filter(node_id.eq(node.node_id))
+        .filter(firmware_type.eq(node.firmware_type))
+        .filter(firmware_version.eq(node.firmware_version))
+        .set(bad.eq(true))
+        .execute(connection)
+}
+
+/// Whether the firmware the node runs was already found bad, so it isn't rolled back again.
+fn runs_bad_firmware(connection: &SqliteConnection, node: &Node) -> Result<bool, diesel::result::Error> {
+    use crate::model::firmware_history::firmware_history::dsl::*;
+    let latest = firmware_history
+        .filter(node_id.eq(node.node_id))
+        .order((observed_at.desc(), id.desc()))
+        .first::<FirmwareHistory>(connection)
+        .optional()?;
+    Ok(match latest {
+        Some(latest) => {
+            latest.bad
+                && latest.firmware_type == node.firmware_type
+                && latest.firmware_version == node.firmware_version
+        }
+        None => false,
+    })
+}
+
+/// A node is rolled back when it went silent shortly after completing an update
+/// and stayed silent for `silence`.
+pub fn should_roll_back(node: &Node, updated_at: NaiveDateTime, silence: Duration, now: NaiveDateTime) -> bool {
+    let runs_desired = node.firmware_type == node.desired_firmware_type
+        && node.firmware_version == node.desired_firmware_version;
+    match node.last_seen {
+        Some(last_seen) => {
+            runs_desired && !node.scheduled && now - last_seen > silence && last_seen - updated_at < silence
+        }
+        None => false,
+    }
+}
+
+/// Periodically rolls back nodes that stopped reporting after an update.
+pub fn monitor(pool: Pool<ConnectionManager<SqliteConnection>>, silence: Duration) -> JoinHandle<()> {
+    thread::spawn(move || loop {
+        match pool.get() {
+            Ok(connection) => check_updated_nodes(&connection, silence),
+            Err(e) => error!("Error while getting connection for rollbacks {:?}", e),
+        }
+        thread::sleep(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+    })
+}
+
+fn check_updated_nodes(connection: &SqliteConnection, silence: Duration) {
+    use crate::model::node::nodes::dsl::*;
+    let now = Utc::now().naive_utc();
+    let idle_nodes = match nodes.filter(scheduled.eq(false)).load::<Node>(connection) {
+        Ok(idle_nodes) => idle_nodes,
+        Err(e) => {
+            error!("Error while loading nodes for rollbacks {:?}", e);
+            return;
+        }
+    };
+    for node in idle_nodes {
+        match last_update(connection, &node) {
+            Ok(Some(updated_at)) if should_roll_back(&node, updated_at, silence, now) => {
+                match runs_bad_firmware(connection, &node) {
+                    Ok(false) => (),
+                    Ok(true) => continue,
+                    Err(e) => {
+                        error!("Error while loading firmware history of node {} {:?}", node.node_id, e);
+                        continue;
+                    }
+                }
+                warn!("Node {} stopped reporting after its update", node.node_id);
+                // Without a firmware to go back to, the firmware is still marked bad so the
+                // rollback isn't attempted again every check.
+                let result = rollback(connection, &node).and_then(|target| match target {
+                    Some(_) => Ok(()),
+                    None => mark_current_firmware_bad(connection, &node).map(|_| ()),
+                });
+                match result {
+                    Ok(_) => (),
+                    Err(e) => error!("Error while rolling back node {} {:?}", node.node_id, e),
+                }
+            }
+            Ok(_) => (),
+            Err(e) => error!("Error while checking update of node {} {:?}", node.node_id, e),
+        }
+    }
+}
+
+fn last_update(connection: &SqliteConnection, node: &Node) -> Result<Option<NaiveDateTime>, diesel::result::Error> {
+    use crate::model::ota_session::ota_sessions::dsl::*;
+    ota_sessions
+        .select(finished_at)
+        .filter(node_id.eq(node.node_id))
+        .filter(firmware_type.eq(node.firmware_type))
+        .filter(firmware_version.eq(node.firmware_version))
+        .filter(status.eq(OtaStatus::Completed))
+        .order(id.desc())
+        .first::<Option<NaiveDateTime>>(connection)
+        .optional()
+        .map(|updated_at| updated_at.and_then(|updated_at| updated_at))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(minutes: i64) -> NaiveDateTime {
+        NaiveDateTime::from_timestamp(minutes * 60, 0)
+    }
+
+    fn node(last_seen: Option<NaiveDateTime>, scheduled: bool) -> Node {
+        Node {
+            node_id: 1,
+            node_name: "Node".to_owned(),
+            firmware_type: 10,
+            firmware_version: 2,
+            desired_firmware_type: 10,
+            desired_firmware_version: 2,
+            scheduled,
+            last_seen,
+            ..Node::default()
+        }
+    }
+
+    #[test]
+    fn rolls_back_nodes_silent_since_update() {
+        let silence = Duration::minutes(30);
+        assert!(should_roll_back(&node(Some(at(5)), false), at(0), silence, at(40)));
+        assert!(!should_roll_back(&node(Some(at(5)), false), at(0), silence, at(20)));
+        assert!(!should_roll_back(&node(Some(at(5)), true), at(0), silence, at(40)));
+        assert!(!should_roll_back(&node(None, false), at(0), silence, at(40)));
+    }
+
+    #[test]
+    fn nodes_reporting_long_after_update_are_not_rolled_back() {
+        let silence = Duration::minutes(30);
+        assert!(!should_roll_back(&node(Some(at(60)), false), at(0), silence, at(100)));
+    }
+}
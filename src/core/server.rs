@@ -1,5 +1,6 @@
 use std::thread;
 
+use chrono::Duration;
 use diesel::prelude::SqliteConnection;
 use diesel::r2d2::{ConnectionManager, Pool};
 
@@ -7,11 +8,18 @@ use crate::channel;
 use crate::channel::{Receiver, Sender};
 use crate::model::sensor::Sensor;
 
+use super::campaign;
 use super::connection::*;
+use super::inclusion::Inclusion;
 use super::interceptor;
+use super::maintenance_window::MaintenanceWindow;
+use super::message::internal::UnitSystem;
 use super::message::set::SetMessage;
-use super::message_handler::{internal, presentation, set, stream};
+use super::message_handler::{internal, presentation, req, set, stream};
+use super::node_id_policy::NodeIdPolicy;
+use super::rollback;
 
+#[allow(clippy::too_many_arguments)]
 pub fn start(
     gateway_info: ConnectionType,
     controller_info: Option<ConnectionType>,
@@ -21,21 +29,33 @@ pub fn start(
     in_set_sender: Sender<SetMessage>,
     set_message_receiver: Receiver<SetMessage>,
     new_sensor_sender: Sender<(String, Sensor)>,
+    unit_system: UnitSystem,
+    inclusion: Inclusion,
+    node_id_policy: NodeIdPolicy,
+    auto_rollback_after_secs: Option<i64>,
+    max_concurrent_ota: Option<usize>,
+    ota_windows: Vec<MaintenanceWindow>,
+    node_timeout_secs: i64,
 ) {
     let (gateway_sender, gateway_receiver) = channel::unbounded();
     let (stream_sender, stream_receiver) = channel::unbounded();
     let (internal_sender, internal_receiver) = channel::unbounded();
     let (presentation_sender, presentation_receiver) = channel::unbounded();
     let (set_sender, set_receiver) = channel::unbounded();
+    let (req_sender, req_receiver) = channel::unbounded();
 
     let (controller_out_sender, controller_out_receiver) = channel::unbounded();
 
     let stream_response_sender = gateway_out_sender.clone();
     let internal_response_sender = gateway_out_sender.clone();
     let set_response_sender = gateway_out_sender.clone();
+    let req_response_sender = gateway_out_sender.clone();
     let presentation_forward_sender = controller_out_sender.clone();
     let set_forward_sender = controller_out_sender.clone();
     let internal_forward_sender = controller_out_sender.clone();
+    let req_forward_sender = controller_out_sender.clone();
+
+    let connection = pool.get().unwrap();
 
     let message_interceptor = thread::spawn(move || {
         interceptor::intercept(
@@ -44,22 +64,32 @@ pub fn start(
             &internal_sender,
             &presentation_sender,
             &set_sender,
+            &req_sender,
             &controller_out_sender,
+            connection,
         );
     });
 
     let set_message_writer = set::handle_from_controller(set_message_receiver, set_response_sender);
 
-    let set_message_reader = set::handle_from_gateway(set_receiver, in_set_sender, set_forward_sender);
+    let connection = pool.get().unwrap();
 
+    let set_message_reader = set::handle_from_gateway(
+        set_receiver,
+        in_set_sender,
+        set_forward_sender,
+        connection,
+    );
 
     let connection = pool.get().unwrap();
 
     let stream_message_processor = thread::spawn(move || {
-        stream::handle(&stream_receiver, &stream_response_sender, connection);
+        stream::handle(&stream_receiver, &stream_response_sender, connection, max_concurrent_ota, ota_windows);
     });
 
     let connection = pool.get().unwrap();
+    let internal_inclusion = inclusion.clone();
+    let internal_node_id_policy = node_id_policy.clone();
 
     let internal_message_processor = thread::spawn(move || {
         internal::handle(
@@ -67,6 +97,9 @@ pub fn start(
             &internal_response_sender,
             &internal_forward_sender,
             connection,
+            unit_system,
+            &internal_inclusion,
+            &internal_node_id_policy,
         );
     });
 
@@ -78,9 +111,28 @@ pub fn start(
             &presentation_forward_sender,
             connection,
             new_sensor_sender,
+            &inclusion,
+            &node_id_policy,
         );
     });
 
+    let connection = pool.get().unwrap();
+
+    let req_message_processor = thread::spawn(move || {
+        req::handle(
+            &req_receiver,
+            &req_response_sender,
+            &req_forward_sender,
+            connection,
+        );
+    });
+
+    let campaign_monitor = campaign::monitor(pool.clone(), node_timeout_secs);
+    let rollback_monitor = auto_rollback_after_secs
+        .map(|secs| rollback::monitor(pool.clone(), Duration::seconds(secs)));
+    let ota_queue_monitor = max_concurrent_ota
+        .map(|max_concurrent_ota| stream::monitor_queue(pool.clone(), gateway_out_sender.clone(), max_concurrent_ota));
+
     let gateway_read_write = thread::spawn(move || {
         stream_read_write(gateway_info, gateway_sender, gateway_out_receiver);
     });
@@ -109,4 +161,12 @@ pub fn start(
     stream_message_processor.join().unwrap();
     internal_message_processor.join().unwrap();
     presentation_message_processor.join().unwrap();
+    req_message_processor.join().unwrap();
+    campaign_monitor.join().unwrap();
+    if let Some(rollback_monitor) = rollback_monitor {
+        rollback_monitor.join().unwrap();
+    }
+    if let Some(ota_queue_monitor) = ota_queue_monitor {
+        ota_queue_monitor.join().unwrap();
+    }
 }
@@ -0,0 +1,84 @@
+use ::actix::*;
+use actix_web::*;
+use diesel::prelude::*;
+
+use crate::model::battery_level::BatteryLevel;
+use crate::model::db::ConnDsl;
+use crate::model::node::Node;
+
+pub struct GetBatteryLevels {
+    pub node_id: i32,
+}
+
+impl Message for GetBatteryLevels {
+    type Result = Result<Vec<BatteryLevel>, Error>;
+}
+
+impl Handler<GetBatteryLevels> for ConnDsl {
+    type Result = Result<Vec<BatteryLevel>, Error>;
+
+    fn handle(&mut self, request: GetBatteryLevels, _: &mut Self::Context) -> Self::Result {
+        use crate::model::battery_level::battery_levels::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        let levels = battery_levels
+            .filter(node_id.eq(request.node_id))
+            .order(reported_at.desc())
+            .load::<BatteryLevel>(conn)
+            .map_err(error::ErrorInternalServerError)?;
+        Ok(levels)
+    }
+}
+
+pub struct ListLowBatteryNodes {
+    pub threshold: i32,
+}
+
+impl Message for ListLowBatteryNodes {
+    type Result = Result<Vec<Node>, Error>;
+}
+
+impl Handler<ListLowBatteryNodes> for ConnDsl {
+    type Result = Result<Vec<Node>, Error>;
+
+    fn handle(&mut self, request: ListLowBatteryNodes, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        low_battery_nodes(conn, request.threshold).map_err(error::ErrorInternalServerError)
+    }
+}
+
+/// Nodes whose last reported battery level is at most `threshold`, emptiest first.
+fn low_battery_nodes(conn: &SqliteConnection, threshold: i32) -> QueryResult<Vec<Node>> {
+    use crate::model::node::nodes::dsl::*;
+    nodes
+        .filter(battery_level.le(threshold))
+        .order(battery_level.asc())
+        .load::<Node>(conn)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::db;
+
+    #[test]
+    fn low_battery_nodes_are_at_most_threshold_emptiest_first() {
+        use crate::model::node::nodes::dsl::*;
+        let conn = db::test::pool().get().unwrap();
+        for (id, level) in &[(1, Some(50)), (2, Some(20)), (3, None), (4, Some(5))] {
+            diesel::insert_into(nodes)
+                .values(&Node {
+                    node_id: *id,
+                    battery_level: *level,
+                    ..Node::default()
+                })
+                .execute(&conn)
+                .unwrap();
+        }
+        let low: Vec<i32> = low_battery_nodes(&conn, 20)
+            .unwrap()
+            .iter()
+            .map(|node| node.node_id)
+            .collect();
+        assert_eq!(vec![4, 2], low);
+    }
+}
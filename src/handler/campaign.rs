@@ -0,0 +1,250 @@
+use ::actix::*;
+use actix_web::*;
+use chrono::Utc;
+use diesel;
+use diesel::prelude::*;
+
+use crate::core::campaign::{cancel_update, campaign_members, schedule_update};
+use crate::model::campaign::{Campaign, CampaignNode, CampaignStatus, NewCampaign};
+use crate::model::db::ConnDsl;
+use crate::model::firmware::Firmware;
+
+use super::response::Msgs;
+
+const DEFAULT_SOAK_MINUTES: i32 = 60;
+
+#[derive(Serialize)]
+pub struct CampaignDto {
+    #[serde(flatten)]
+    pub campaign: Campaign,
+    pub nodes: Vec<CampaignNode>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewCampaignRequest {
+    pub firmware_type: i32,
+    pub firmware_version: i32,
+    pub node_ids: Vec<i32>,
+    #[serde(default)]
+    pub canary_node_ids: Vec<i32>,
+    pub soak_minutes: Option<i32>,
+}
+
+impl Message for NewCampaignRequest {
+    type Result = Result<CampaignDto, Msgs>;
+}
+
+impl Handler<NewCampaignRequest> for ConnDsl {
+    type Result = Result<CampaignDto, Msgs>;
+
+    fn handle(&mut self, request: NewCampaignRequest, _: &mut Self::Context) -> Self::Result {
+        use crate::model::campaign::campaigns::dsl::*;
+        let conn = self.0.get().map_err(|_| internal_error())?;
+        let mut target_ids: Vec<i32> = Vec::new();
+        for target_id in request.node_ids.iter().chain(&request.canary_node_ids) {
+            if !target_ids.contains(target_id) {
+                target_ids.push(*target_id);
+            }
+        }
+        if target_ids.is_empty() {
+            return Err(bad_request("node_ids should not be empty."));
+        }
+        let soak = request.soak_minutes.unwrap_or(DEFAULT_SOAK_MINUTES);
+        if soak < 1 {
+            return Err(bad_request("soak_minutes should be at least 1."));
+        }
+        if crate::model::firmware::firmwares::table
+            .find((request.firmware_type, request.firmware_version))
+            .first::<Firmware>(&conn)
+            .optional()
+            .map_err(|_| internal_error())?
+            .is_none()
+        {
+            return Err(bad_request("firmware not present."));
+        }
+        let existing_count: i64 = crate::model::node::nodes::table
+            .filter(crate::model::node::nodes::node_id.eq_any(&target_ids))
+            .count()
+            .get_result(&conn)
+            .map_err(|_| internal_error())?;
+        if existing_count != target_ids.len() as i64 {
+            return Err(bad_request("some of the nodes are not present."));
+        }
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::insert_into(campaigns)
+                .values(&NewCampaign {
+                    firmware_type: request.firmware_type,
+                    firmware_version: request.firmware_version,
+                    soak_minutes: soak,
+                    status: CampaignStatus::Running,
+                    created_at: Utc::now().naive_utc(),
+                })
+                .execute(&conn)?;
+            let campaign = campaigns.order(id.desc()).first::<Campaign>(&conn)?;
+            let members: Vec<CampaignNode> = target_ids
+                .iter()
+                .map(|target_id| CampaignNode {
+                    campaign_id: campaign.id,
+                    node_id: *target_id,
+                    canary: request.canary_node_ids.contains(target_id),
+                })
+                .collect();
+            for member in &members {
+                diesel::insert_into(crate::model::campaign::campaign_nodes::table)
+                    .values(member)
+                    .execute(&conn)?;
+            }
+            // Without canaries there is nothing to wait for, all nodes are updated at once.
+            if request.canary_node_ids.is_empty() {
+                schedule_update(&conn, &target_ids, campaign.firmware_type, campaign.firmware_version)?;
+                diesel::update(campaigns.find(campaign.id))
+                    .set(promoted_at.eq(campaign.created_at))
+                    .execute(&conn)?;
+            } else {
+                schedule_update(
+                    &conn,
+                    &request.canary_node_ids,
+                    campaign.firmware_type,
+                    campaign.firmware_version,
+                )?;
+            }
+            let campaign = campaigns.find(campaign.id).first::<Campaign>(&conn)?;
+            Ok(CampaignDto {
+                campaign,
+                nodes: members,
+            })
+        })
+            .map_err(|e| {
+                error!("Error while creating campaign {:?}", e);
+                internal_error()
+            })
+    }
+}
+
+pub struct ListCampaigns;
+
+impl Message for ListCampaigns {
+    type Result = Result<Vec<Campaign>, Error>;
+}
+
+impl Handler<ListCampaigns> for ConnDsl {
+    type Result = Result<Vec<Campaign>, Error>;
+
+    fn handle(&mut self, _list_campaigns: ListCampaigns, _: &mut Self::Context) -> Self::Result {
+        use crate::model::campaign::campaigns::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        campaigns
+            .order(created_at.desc())
+            .load::<Campaign>(conn)
+            .map_err(error::ErrorInternalServerError)
+    }
+}
+
+pub struct GetCampaign {
+    pub campaign_id: i32,
+}
+
+impl Message for GetCampaign {
+    type Result = Result<Option<CampaignDto>, Error>;
+}
+
+impl Handler<GetCampaign> for ConnDsl {
+    type Result = Result<Option<CampaignDto>, Error>;
+
+    fn handle(&mut self, request: GetCampaign, _: &mut Self::Context) -> Self::Result {
+        use crate::model::campaign::campaigns::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        let campaign = campaigns
+            .find(request.campaign_id)
+            .first::<Campaign>(conn)
+            .optional()
+            .map_err(error::ErrorInternalServerError)?;
+        match campaign {
+            Some(campaign) => {
+                let nodes = campaign_members(conn, campaign.id).map_err(error::ErrorInternalServerError)?;
+                Ok(Some(CampaignDto { campaign, nodes }))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CampaignAction {
+    Pause,
+    Resume,
+    Abort,
+}
+
+pub struct ChangeCampaign {
+    pub campaign_id: i32,
+    pub action: CampaignAction,
+}
+
+impl Message for ChangeCampaign {
+    type Result = Result<Msgs, diesel::result::Error>;
+}
+
+impl Handler<ChangeCampaign> for ConnDsl {
+    type Result = Result<Msgs, diesel::result::Error>;
+
+    fn handle(&mut self, request: ChangeCampaign, _: &mut Self::Context) -> Self::Result {
+        use crate::model::campaign::campaigns::dsl::*;
+        let conn = match self.0.get() {
+            Ok(conn) => conn,
+            Err(_) => return Ok(internal_error()),
+        };
+        let campaign = match campaigns.find(request.campaign_id).first::<Campaign>(&conn).optional()? {
+            Some(campaign) => campaign,
+            None => return Ok(Msgs {
+                status: 404,
+                message: "campaign not present.".to_string(),
+            }),
+        };
+        let next_status = match (request.action, campaign.status) {
+            (CampaignAction::Pause, CampaignStatus::Running) => CampaignStatus::Paused,
+            (CampaignAction::Resume, CampaignStatus::Paused) => CampaignStatus::Running,
+            (CampaignAction::Abort, CampaignStatus::Running)
+            | (CampaignAction::Abort, CampaignStatus::Paused) => CampaignStatus::Aborted,
+            _ => return Ok(bad_request(&format!(
+                "can't {:?} a {:?} campaign.",
+                request.action, campaign.status
+            ).to_lowercase())),
+        };
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            if next_status == CampaignStatus::Aborted {
+                let member_ids: Vec<i32> = campaign_members(&conn, campaign.id)?
+                    .iter()
+                    .map(|member| member.node_id)
+                    .collect();
+                let cancelled = cancel_update(&conn, &member_ids, campaign.firmware_type, campaign.firmware_version)?;
+                info!("Aborted campaign {}, cancelled update of {} nodes", campaign.id, cancelled);
+                diesel::update(campaigns.find(campaign.id))
+                    .set((status.eq(next_status), finished_at.eq(Utc::now().naive_utc())))
+                    .execute(&conn)?;
+            } else {
+                diesel::update(campaigns.find(campaign.id))
+                    .set(status.eq(next_status))
+                    .execute(&conn)?;
+            }
+            Ok(Msgs {
+                status: 200,
+                message: format!("campaign is {:?}.", next_status).to_lowercase(),
+            })
+        })
+    }
+}
+
+fn bad_request(message: &str) -> Msgs {
+    Msgs {
+        status: 400,
+        message: message.to_string(),
+    }
+}
+
+fn internal_error() -> Msgs {
+    Msgs {
+        status: 500,
+        message: "internal server error.".to_string(),
+    }
+}
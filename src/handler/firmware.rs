@@ -1,13 +1,14 @@
 use ::actix::*;
 use actix_web::*;
+use chrono::{NaiveDateTime, Utc};
 use diesel;
 use diesel::prelude::*;
 use diesel::result::DatabaseErrorKind;
 use diesel::result::Error::DatabaseError;
 
-use crate::model;
 use crate::model::db::ConnDsl;
-use crate::model::firmware::Firmware;
+use crate::model::firmware::{Board, Firmware};
+use crate::model::node::Node;
 
 use super::response::Msgs;
 
@@ -18,6 +19,12 @@ pub struct FirmwareDto {
     pub firmware_name: String,
     pub blocks: i32,
     pub crc: i32,
+    pub board: Board,
+    pub description: Option<String>,
+    pub uploaded_at: Option<NaiveDateTime>,
+    pub sha256: Option<String>,
+    pub filename: Option<String>,
+    pub git_revision: Option<String>,
 }
 
 pub enum CreateOrUpdate {
@@ -49,6 +56,12 @@ impl Handler<CreateOrUpdate> for ConnDsl {
                         blocks: new_firmware.blocks,
                         crc: new_firmware.crc,
                         data: new_firmware.data,
+                        board: new_firmware.board,
+                        description: new_firmware.description,
+                        uploaded_at: Some(Utc::now().naive_utc()),
+                        sha256: new_firmware.sha256,
+                        filename: new_firmware.filename,
+                        git_revision: new_firmware.git_revision,
                     };
 
                     diesel::insert_into(firmwares)
@@ -87,6 +100,12 @@ impl Handler<CreateOrUpdate> for ConnDsl {
                             blocks.eq(new_firmware.blocks),
                             crc.eq(new_firmware.crc),
                             data.eq(new_firmware.data),
+                            board.eq(new_firmware.board),
+                            description.eq(new_firmware.description),
+                            uploaded_at.eq(Utc::now().naive_utc()),
+                            sha256.eq(new_firmware.sha256),
+                            filename.eq(new_firmware.filename),
+                            git_revision.eq(new_firmware.git_revision),
                         ))
                         .execute(&conn)
                         .map_err(|_| Msgs {
@@ -117,30 +136,11 @@ pub struct NewFirmware {
     pub blocks: i32,
     pub crc: i32,
     pub data: Vec<u8>,
-}
-
-impl NewFirmware {
-    pub fn build(
-        firmware_type: i32,
-        firmware_version: i32,
-        name: String,
-        mut data: Vec<u8>,
-    ) -> NewFirmware {
-        let pads: usize = data.len() % 128; // 128 bytes per page for atmega328
-        for _ in 0..(128 - pads) {
-            data.push(255);
-        }
-        let blocks: i32 = data.len() as i32 / model::firmware::FIRMWARE_BLOCK_SIZE;
-        let crc = i32::from(Firmware::compute_crc(&data));
-        NewFirmware {
-            firmware_type,
-            firmware_version,
-            blocks,
-            data,
-            name,
-            crc,
-        }
-    }
+    pub board: Board,
+    pub description: Option<String>,
+    pub sha256: Option<String>,
+    pub filename: Option<String>,
+    pub git_revision: Option<String>,
 }
 
 fn auto_update_nodes(connection: &SqliteConnection, new_firmware: Firmware) -> Result<Msgs, Msgs> {
@@ -171,18 +171,27 @@ fn auto_update_nodes(connection: &SqliteConnection, new_firmware: Firmware) -> R
 }
 
 impl FirmwareDto {
-    fn new(firmware: &Firmware) -> FirmwareDto {
+    pub fn new(firmware: &Firmware) -> FirmwareDto {
         FirmwareDto {
             firmware_type: firmware.firmware_type,
             firmware_version: firmware.firmware_version,
             firmware_name: firmware.name.clone(),
             blocks: firmware.blocks,
             crc: firmware.crc,
+            board: firmware.board,
+            description: firmware.description.clone(),
+            uploaded_at: firmware.uploaded_at,
+            sha256: firmware.sha256.clone(),
+            filename: firmware.filename.clone(),
+            git_revision: firmware.git_revision.clone(),
         }
     }
 }
 
-pub struct ListFirmwares;
+pub struct ListFirmwares {
+    pub firmware_type: Option<i32>,
+    pub board: Option<Board>,
+}
 
 impl Message for ListFirmwares {
     type Result = Result<Vec<FirmwareDto>, Error>;
@@ -191,10 +200,17 @@ impl Message for ListFirmwares {
 impl Handler<ListFirmwares> for ConnDsl {
     type Result = Result<Vec<FirmwareDto>, Error>;
 
-    fn handle(&mut self, _list_firmwares: ListFirmwares, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, list_firmwares: ListFirmwares, _: &mut Self::Context) -> Self::Result {
         use crate::model::firmware::firmwares::dsl::*;
         let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
-        let existing_firmwares = firmwares
+        let mut query = firmwares.into_boxed();
+        if let Some(listed_firmware_type) = list_firmwares.firmware_type {
+            query = query.filter(firmware_type.eq(listed_firmware_type));
+        }
+        if let Some(listed_board) = list_firmwares.board {
+            query = query.filter(board.eq(listed_board));
+        }
+        let existing_firmwares = query
             .load::<Firmware>(conn)
             .map_err(error::ErrorInternalServerError)?;
         Ok(existing_firmwares
@@ -208,6 +224,8 @@ impl Handler<ListFirmwares> for ConnDsl {
 pub struct DeleteFirmware {
     pub firmware_type: i32,
     pub firmware_version: i32,
+    /// Deletes the firmware even when nodes still want it.
+    pub force: bool,
 }
 
 impl Message for DeleteFirmware {
@@ -220,7 +238,21 @@ impl Handler<DeleteFirmware> for ConnDsl {
     fn handle(&mut self, delete_firmware: DeleteFirmware, _: &mut Self::Context) -> Self::Result {
         use crate::model::firmware::firmwares::dsl::*;
         match &self.0.get() {
-            Ok(conn) => {
+            // Nodes can't be given the firmware as desired one between the check and the delete.
+            Ok(conn) => conn.transaction(|| {
+                if !delete_firmware.force {
+                    let wanting_node_ids = nodes_wanting(conn, delete_firmware.firmware_type, delete_firmware.firmware_version)?;
+                    if !wanting_node_ids.is_empty() {
+                        let node_ids: Vec<String> = wanting_node_ids.iter().map(i32::to_string).collect();
+                        return Ok(Msgs {
+                            status: 409,
+                            message: format!(
+                                "delete failed. firmware is the desired firmware of nodes {}, use force=true to delete it anyway",
+                                node_ids.join(", ")
+                            ),
+                        });
+                    }
+                }
                 let updated = diesel::delete(firmwares)
                     .filter(&firmware_type.eq(&delete_firmware.firmware_type))
                     .filter(&firmware_version.eq(&delete_firmware.firmware_version))
@@ -236,7 +268,7 @@ impl Handler<DeleteFirmware> for ConnDsl {
                     }),
                     Err(e) => Err(e),
                 }
-            }
+            }),
             Err(_) => Ok(Msgs {
                 status: 500,
                 message: "delete firmware failed. internal server error".to_string(),
@@ -245,6 +277,73 @@ impl Handler<DeleteFirmware> for ConnDsl {
     }
 }
 
+fn nodes_wanting(
+    connection: &SqliteConnection,
+    _firmware_type: i32,
+    _firmware_version: i32,
+) -> Result<Vec<i32>, diesel::result::Error> {
+    use crate::model::node::nodes::dsl::*;
+    nodes
+        .select(node_id)
+        .filter(desired_firmware_type.eq(_firmware_type))
+        .filter(desired_firmware_version.eq(_firmware_version))
+        .order(node_id.asc())
+        .load::<i32>(connection)
+}
+
+/// Node running or wanting a firmware.
+#[derive(Serialize, Debug)]
+pub struct FirmwareNode {
+    pub node_id: i32,
+    pub node_name: String,
+    /// The node reported running the firmware.
+    pub runs: bool,
+    /// The firmware is the desired firmware of the node.
+    pub desired: bool,
+    pub scheduled: bool,
+}
+
+pub struct GetFirmwareNodes {
+    pub firmware_type: i32,
+    pub firmware_version: i32,
+}
+
+impl Message for GetFirmwareNodes {
+    type Result = Result<Vec<FirmwareNode>, Error>;
+}
+
+impl Handler<GetFirmwareNodes> for ConnDsl {
+    type Result = Result<Vec<FirmwareNode>, Error>;
+
+    fn handle(&mut self, request: GetFirmwareNodes, _: &mut Self::Context) -> Self::Result {
+        use crate::model::node::nodes::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        let runs = firmware_type
+            .eq(request.firmware_type)
+            .and(firmware_version.eq(request.firmware_version));
+        let desired = desired_firmware_type
+            .eq(request.firmware_type)
+            .and(desired_firmware_version.eq(request.firmware_version));
+        let firmware_nodes = nodes
+            .filter(runs.or(desired))
+            .order(node_id.asc())
+            .load::<Node>(conn)
+            .map_err(error::ErrorInternalServerError)?;
+        Ok(firmware_nodes
+            .into_iter()
+            .map(|node| FirmwareNode {
+                runs: node.firmware_type == request.firmware_type
+                    && node.firmware_version == request.firmware_version,
+                desired: node.desired_firmware_type == request.firmware_type
+                    && node.desired_firmware_version == request.firmware_version,
+                scheduled: node.scheduled,
+                node_id: node.node_id,
+                node_name: node.node_name,
+            })
+            .collect())
+    }
+}
+
 pub struct GetFirmware {
     pub firmware_type: i32,
     pub firmware_version: i32,
@@ -1,4 +1,9 @@
+pub mod battery;
+pub mod campaign;
 pub mod firmware;
 pub mod node;
+pub mod ota;
+pub mod ping;
+pub mod reservation;
 pub mod response;
 pub mod sensor;
@@ -1,12 +1,17 @@
 use ::actix::*;
 use actix_web::*;
+use chrono::{NaiveDateTime, Utc};
 use diesel;
 use diesel::prelude::*;
 use diesel::result::DatabaseErrorKind;
 use diesel::result::Error::DatabaseError;
 
+use crate::core::message::internal::UnitSystem;
+use crate::core::rollback::rollback;
 use crate::model::db::ConnDsl;
-use crate::model::node::Node;
+use crate::model::firmware_history::FirmwareHistory;
+use crate::model::node::{Node, NodeStatus};
+use crate::model::node_id_reservation::{deleted_nodes, DeletedNode};
 
 use super::response::Msgs;
 
@@ -18,6 +23,23 @@ pub struct NewNode {
     pub firmware_version: i32,
     pub auto_update: bool,
     pub scheduled: bool,
+    pub timeout_secs: Option<i32>,
+    pub unit_system: Option<UnitSystem>,
+    pub ota_window: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct NodeDto {
+    #[serde(flatten)]
+    pub node: Node,
+    pub status: NodeStatus,
+}
+
+impl NodeDto {
+    pub fn new(node: Node, default_timeout_secs: i64, now: NaiveDateTime) -> NodeDto {
+        let status = node.status(default_timeout_secs, now);
+        NodeDto { node, status }
+    }
 }
 
 pub struct GetNode {
@@ -68,6 +90,9 @@ pub struct NodeUpdate {
     pub firmware_version: i32,
     pub auto_update: bool,
     pub scheduled: bool,
+    pub timeout_secs: Option<i32>,
+    pub unit_system: Option<UnitSystem>,
+    pub ota_window: Option<String>,
 }
 
 impl Message for NodeUpdate {
@@ -89,6 +114,9 @@ impl Handler<NodeUpdate> for ConnDsl {
                         desired_firmware_version.eq(node_update.firmware_version),
                         auto_update.eq(node_update.auto_update),
                         scheduled.eq(node_update.scheduled),
+                        timeout_secs.eq(node_update.timeout_secs),
+                        unit_system.eq(node_update.unit_system),
+                        ota_window.eq(node_update.ota_window),
                     ))
                     .execute(conn);
                 match updated {
@@ -122,10 +150,18 @@ impl Handler<DeleteNode> for ConnDsl {
                     .filter(&node_id.eq(&delete_node.node_id))
                     .execute(conn);
                 match updated {
-                    Ok(1) => Ok(Msgs {
-                        status: 200,
-                        message: "deleted node.".to_string(),
-                    }),
+                    Ok(1) => {
+                        diesel::replace_into(deleted_nodes::table)
+                            .values(&DeletedNode {
+                                node_id: delete_node.node_id,
+                                deleted_at: Utc::now().naive_utc(),
+                            })
+                            .execute(conn)?;
+                        Ok(Msgs {
+                            status: 200,
+                            message: "deleted node.".to_string(),
+                        })
+                    }
                     Ok(_) => Ok(Msgs {
                         status: 400,
                         message: "delete failed. node id is not present".to_string(),
@@ -158,6 +194,16 @@ impl Handler<NewNode> for ConnDsl {
                     auto_update: new_node.auto_update,
                     scheduled: new_node.scheduled,
                     parent_node_id: 0,
+                    last_seen: None,
+                    timeout_secs: new_node.timeout_secs,
+                    battery_level: None,
+                    sketch_name: None,
+                    sketch_version: None,
+                    library_version: None,
+                    is_repeater: false,
+                    unit_system: new_node.unit_system,
+                    ota_window: new_node.ota_window,
+                    last_boot_at: None,
                 };
 
                 let result = diesel::insert_into(nodes).values(&new_node).execute(conn);
@@ -196,3 +242,66 @@ impl Handler<GetNode> for ConnDsl {
         }
     }
 }
+
+pub struct RollbackNode {
+    pub node_id: i32,
+}
+
+impl Message for RollbackNode {
+    type Result = Result<Msgs, diesel::result::Error>;
+}
+
+impl Handler<RollbackNode> for ConnDsl {
+    type Result = Result<Msgs, diesel::result::Error>;
+
+    fn handle(&mut self, rollback_node: RollbackNode, _: &mut Self::Context) -> Self::Result {
+        use crate::model::node::nodes::dsl::*;
+        match &self.0.get() {
+            Ok(conn) => match nodes.find(rollback_node.node_id).first::<Node>(conn).optional()? {
+                Some(node) => match rollback(conn, &node)? {
+                    Some(target) => Ok(Msgs {
+                        status: 200,
+                        message: format!(
+                            "rolling back to firmware type {} version {}.",
+                            target.firmware_type, target.firmware_version
+                        ),
+                    }),
+                    None => Ok(Msgs {
+                        status: 400,
+                        message: "no previous good firmware to roll back to.".to_string(),
+                    }),
+                },
+                None => Ok(Msgs {
+                    status: 404,
+                    message: "node not present.".to_string(),
+                }),
+            },
+            Err(_) => Ok(Msgs {
+                status: 500,
+                message: "rollback failed. internal server error".to_string(),
+            }),
+        }
+    }
+}
+
+pub struct GetFirmwareHistory {
+    pub node_id: i32,
+}
+
+impl Message for GetFirmwareHistory {
+    type Result = Result<Vec<FirmwareHistory>, Error>;
+}
+
+impl Handler<GetFirmwareHistory> for ConnDsl {
+    type Result = Result<Vec<FirmwareHistory>, Error>;
+
+    fn handle(&mut self, request: GetFirmwareHistory, _: &mut Self::Context) -> Self::Result {
+        use crate::model::firmware_history::firmware_history::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        firmware_history
+            .filter(node_id.eq(request.node_id))
+            .order((observed_at.desc(), id.desc()))
+            .load::<FirmwareHistory>(conn)
+            .map_err(error::ErrorInternalServerError)
+    }
+}
@@ -0,0 +1,71 @@
+use ::actix::*;
+use actix_web::*;
+use diesel::prelude::*;
+
+use crate::model::db::ConnDsl;
+use crate::model::ota_session::{OtaSession, OtaStatus, QueuedOta};
+
+#[derive(Serialize)]
+pub struct OtaSessionDto {
+    #[serde(flatten)]
+    pub session: OtaSession,
+    pub progress: f64,
+}
+
+impl From<OtaSession> for OtaSessionDto {
+    fn from(session: OtaSession) -> OtaSessionDto {
+        let progress = session.progress();
+        OtaSessionDto { session, progress }
+    }
+}
+
+pub struct GetOtaSessions {
+    pub node_id: Option<i32>,
+    pub status: Option<OtaStatus>,
+}
+
+impl Message for GetOtaSessions {
+    type Result = Result<Vec<OtaSession>, Error>;
+}
+
+impl Handler<GetOtaSessions> for ConnDsl {
+    type Result = Result<Vec<OtaSession>, Error>;
+
+    fn handle(&mut self, request: GetOtaSessions, _: &mut Self::Context) -> Self::Result {
+        use crate::model::ota_session::ota_sessions::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        let mut query = ota_sessions.into_boxed();
+        if let Some(session_node_id) = request.node_id {
+            query = query.filter(node_id.eq(session_node_id));
+        }
+        if let Some(session_status) = request.status {
+            query = query.filter(status.eq(session_status));
+        }
+        let sessions = query
+            .order(started_at.desc())
+            .load::<OtaSession>(conn)
+            .map_err(error::ErrorInternalServerError)?;
+        Ok(sessions)
+    }
+}
+
+/// Nodes waiting for a free ota slot, first to be updated first.
+pub struct GetOtaQueue;
+
+impl Message for GetOtaQueue {
+    type Result = Result<Vec<QueuedOta>, Error>;
+}
+
+impl Handler<GetOtaQueue> for ConnDsl {
+    type Result = Result<Vec<QueuedOta>, Error>;
+
+    fn handle(&mut self, _: GetOtaQueue, _: &mut Self::Context) -> Self::Result {
+        use crate::model::ota_session::ota_queue::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        let queue = ota_queue
+            .order(queued_at.asc())
+            .load::<QueuedOta>(conn)
+            .map_err(error::ErrorInternalServerError)?;
+        Ok(queue)
+    }
+}
@@ -0,0 +1,72 @@
+use ::actix::*;
+use actix_web::*;
+use chrono::Utc;
+use diesel;
+use diesel::prelude::*;
+
+use crate::model::db::ConnDsl;
+use crate::model::node::Node;
+use crate::model::node_ping::{NewNodePing, NodePing};
+
+/// Records a ping about to be sent, `None` when the node is not present.
+pub struct StartPing {
+    pub node_id: i32,
+}
+
+impl Message for StartPing {
+    type Result = Result<Option<NodePing>, Error>;
+}
+
+impl Handler<StartPing> for ConnDsl {
+    type Result = Result<Option<NodePing>, Error>;
+
+    fn handle(&mut self, start_ping: StartPing, _: &mut Self::Context) -> Self::Result {
+        use crate::model::node::nodes::dsl::nodes;
+        use crate::model::node_ping::node_pings::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        let node = nodes
+            .find(start_ping.node_id)
+            .first::<Node>(conn)
+            .optional()
+            .map_err(error::ErrorInternalServerError)?;
+        if node.is_none() {
+            return Ok(None);
+        }
+        diesel::insert_into(node_pings)
+            .values(&NewNodePing {
+                node_id: start_ping.node_id,
+                sent_at: Utc::now().naive_utc(),
+            })
+            .execute(conn)
+            .map_err(error::ErrorInternalServerError)?;
+        let ping = node_pings
+            .filter(node_id.eq(start_ping.node_id))
+            .order(id.desc())
+            .first::<NodePing>(conn)
+            .map_err(error::ErrorInternalServerError)?;
+        Ok(Some(ping))
+    }
+}
+
+pub struct GetPings {
+    pub node_id: i32,
+}
+
+impl Message for GetPings {
+    type Result = Result<Vec<NodePing>, Error>;
+}
+
+impl Handler<GetPings> for ConnDsl {
+    type Result = Result<Vec<NodePing>, Error>;
+
+    fn handle(&mut self, request: GetPings, _: &mut Self::Context) -> Self::Result {
+        use crate::model::node_ping::node_pings::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        let pings = node_pings
+            .filter(node_id.eq(request.node_id))
+            .order(sent_at.desc())
+            .load::<NodePing>(conn)
+            .map_err(error::ErrorInternalServerError)?;
+        Ok(pings)
+    }
+}
@@ -0,0 +1,119 @@
+use ::actix::*;
+use actix_web::*;
+use chrono::Utc;
+use diesel;
+use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
+use diesel::result::Error::DatabaseError;
+
+use crate::model::db::ConnDsl;
+use crate::model::node::Node;
+use crate::model::node_id_reservation::NodeIdReservation;
+
+use super::response::Msgs;
+
+pub struct ListReservations;
+
+impl Message for ListReservations {
+    type Result = Result<Vec<NodeIdReservation>, Error>;
+}
+
+impl Handler<ListReservations> for ConnDsl {
+    type Result = Result<Vec<NodeIdReservation>, Error>;
+
+    fn handle(&mut self, _list_reservations: ListReservations, _: &mut Self::Context) -> Self::Result {
+        use crate::model::node_id_reservation::node_id_reservations::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        let reservations = node_id_reservations
+            .order(node_id.asc())
+            .load::<NodeIdReservation>(conn)
+            .map_err(error::ErrorInternalServerError)?;
+        Ok(reservations)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewReservation {
+    pub node_id: i32,
+    pub note: Option<String>,
+}
+
+impl Message for NewReservation {
+    type Result = Result<Msgs, diesel::result::Error>;
+}
+
+impl Handler<NewReservation> for ConnDsl {
+    type Result = Result<Msgs, diesel::result::Error>;
+
+    fn handle(&mut self, new_reservation: NewReservation, _: &mut Self::Context) -> Self::Result {
+        use crate::model::node::nodes::dsl::nodes;
+        use crate::model::node_id_reservation::node_id_reservations::dsl::*;
+        match &self.0.get() {
+            Ok(conn) => {
+                if nodes.find(new_reservation.node_id).first::<Node>(conn).optional()?.is_some() {
+                    return Ok(Msgs {
+                        status: 400,
+                        message: "node_id already used by a node.".to_string(),
+                    });
+                }
+                let reservation = NodeIdReservation {
+                    node_id: new_reservation.node_id,
+                    note: new_reservation.note,
+                    reserved_at: Utc::now().naive_utc(),
+                };
+                match diesel::insert_into(node_id_reservations)
+                    .values(&reservation)
+                    .execute(conn)
+                    {
+                        Ok(_) => Ok(Msgs {
+                            status: 200,
+                            message: "reserved node id.".to_string(),
+                        }),
+                        Err(DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(Msgs {
+                            status: 400,
+                            message: "node_id already reserved.".to_string(),
+                        }),
+                        Err(e) => Err(e),
+                    }
+            }
+            Err(_) => Ok(Msgs {
+                status: 500,
+                message: "reservation failed. internal server error".to_string(),
+            }),
+        }
+    }
+}
+
+pub struct DeleteReservation {
+    pub node_id: i32,
+}
+
+impl Message for DeleteReservation {
+    type Result = Result<Msgs, diesel::result::Error>;
+}
+
+impl Handler<DeleteReservation> for ConnDsl {
+    type Result = Result<Msgs, diesel::result::Error>;
+
+    fn handle(&mut self, delete_reservation: DeleteReservation, _: &mut Self::Context) -> Self::Result {
+        use crate::model::node_id_reservation::node_id_reservations::dsl::*;
+        match &self.0.get() {
+            Ok(conn) => match diesel::delete(node_id_reservations.find(delete_reservation.node_id))
+                .execute(conn)?
+                {
+                    1 => Ok(Msgs {
+                        status: 200,
+                        message: "deleted reservation.".to_string(),
+                    }),
+                    _ => Ok(Msgs {
+                        status: 404,
+                        message: "node id is not reserved".to_string(),
+                    }),
+                },
+            Err(_) => Ok(Msgs {
+                status: 500,
+                message: "delete failed. internal server error".to_string(),
+            }),
+        }
+    }
+}
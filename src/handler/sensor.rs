@@ -1,13 +1,19 @@
 use ::actix::*;
 use actix_web::*;
+use chrono::NaiveDateTime;
 use diesel;
 use diesel::prelude::*;
 
+use crate::core::message::set::SetReqType;
 use crate::model::db::ConnDsl;
 use crate::model::sensor::Sensor;
+use crate::model::sensor_value::{self, AggregatedValue, SensorValue};
 
 use super::response::Msgs;
 
+/// Raw values returned at most per request, older values come first.
+pub const MAX_RAW_VALUES: i64 = 10_000;
+
 pub struct GetSensor {
     pub node_id: i32,
     pub child_sensor_id: i32,
@@ -94,3 +100,54 @@ impl Handler<GetSensor> for ConnDsl {
             }
     }
 }
+
+pub struct GetSensorValues {
+    pub node_id: i32,
+    pub child_sensor_id: i32,
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub set_type: Option<SetReqType>,
+    pub bucket: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum SensorValues {
+    Raw(Vec<SensorValue>),
+    Aggregated(Vec<AggregatedValue>),
+}
+
+impl Message for GetSensorValues {
+    type Result = Result<SensorValues, Error>;
+}
+
+impl Handler<GetSensorValues> for ConnDsl {
+    type Result = Result<SensorValues, Error>;
+
+    fn handle(&mut self, request: GetSensorValues, _: &mut Self::Context) -> Self::Result {
+        use crate::model::sensor_value::sensor_values::dsl::*;
+        let conn = &self.0.get().map_err(error::ErrorInternalServerError)?;
+        let mut query = sensor_values
+            .filter(node_id.eq(request.node_id))
+            .filter(child_sensor_id.eq(request.child_sensor_id))
+            .filter(received_at.ge(request.from))
+            .filter(received_at.le(request.to))
+            .into_boxed();
+        if let Some(requested_type) = request.set_type {
+            query = query.filter(set_type.eq(requested_type));
+        }
+        if request.bucket.is_none() {
+            query = query.limit(MAX_RAW_VALUES);
+        }
+        let values = query
+            .order(received_at.asc())
+            .load::<SensorValue>(conn)
+            .map_err(error::ErrorInternalServerError)?;
+        Ok(match request.bucket {
+            Some(bucket) => {
+                SensorValues::Aggregated(sensor_value::aggregate(&values, bucket))
+            }
+            None => SensorValues::Raw(values),
+        })
+    }
+}
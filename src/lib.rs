@@ -5,6 +5,9 @@ extern crate enum_primitive;
 extern crate diesel;
 #[macro_use]
 extern crate diesel_derive_enum;
+#[cfg(test)]
+#[macro_use]
+extern crate diesel_migrations;
 #[macro_use]
 extern crate serde_derive;
 use crossbeam_channel as channel;
@@ -16,11 +16,16 @@ use diesel::r2d2::{ConnectionManager, Pool};
 use env_logger;
 use num_cpus;
 
-use myscontroller_rs::api::{firmware, index, node, sensor};
+use myscontroller_rs::api::{battery, campaign, firmware, inclusion, index, node, ota, ping, reservation, sensor, topology};
 use myscontroller_rs::api::index::AppState;
 use myscontroller_rs::core::{connection, server as mys_controller};
 use myscontroller_rs::core::connection::ConnectionType;
+use myscontroller_rs::core::inclusion::Inclusion;
+use myscontroller_rs::core::maintenance_window::{MaintenanceWindow, parse_windows};
+use myscontroller_rs::core::message::internal::UnitSystem;
+use myscontroller_rs::core::node_id_policy::{NodeIdPolicy, parse_ranges};
 use myscontroller_rs::model::db;
+use myscontroller_rs::model::node::MAX_TIMEOUT_SECS;
 use myscontroller_rs::wot;
 
 mod config;
@@ -41,8 +46,14 @@ fn main() {
     env_logger::init();
 
     let database_url = server_configs(&conf);
+    let node_timeout_secs = node_timeout(&conf);
+    let inclusion = Inclusion::new(inclusion_always_open(&conf));
+    let app_inclusion = inclusion.clone();
+    let node_id_policy = node_id_policy(&conf);
+    let app_node_id_policy = node_id_policy.clone();
     let manager = ConnectionManager::<SqliteConnection>::new(database_url);
     let conn = Pool::builder()
+        .connection_customizer(Box::new(db::ConnectionOptions))
         .build(manager)
         .expect("Failed to create pool.");
     let conn_clone = conn.clone();
@@ -57,6 +68,9 @@ fn main() {
         App::with_state(AppState {
             db: database_addr.clone(),
             reset_sender: reset_signal_sender.clone(),
+            node_timeout_secs,
+            inclusion: app_inclusion.clone(),
+            node_id_policy: app_node_id_policy.clone(),
         })
             .middleware(middleware::Logger::default())
             .configure(|app| {
@@ -77,6 +91,64 @@ fn main() {
                     .resource("/nodes/{node_id}/reboot", |r| {
                         r.method(Method::POST).h(node::reboot_node);
                     })
+                    .resource("/nodes/{node_id}/heartbeat", |r| {
+                        r.method(Method::POST).h(node::heartbeat_node);
+                    })
+                    .resource("/nodes/{node_id}/battery", |r| {
+                        r.method(Method::GET).h(battery::node_history);
+                    })
+                    .resource("/nodes/{node_id}/ping", |r| {
+                        r.method(Method::POST).h(ping::ping_node);
+                    })
+                    .resource("/nodes/{node_id}/pings", |r| {
+                        r.method(Method::GET).h(ping::node_history);
+                    })
+                    .resource("/nodes/{node_id}/firmwares", |r| {
+                        r.method(Method::GET).h(node::firmware_history);
+                    })
+                    .resource("/nodes/{node_id}/rollback", |r| {
+                        r.method(Method::POST).h(node::rollback_node);
+                    })
+                    .resource("/nodes/{node_id}/ota", |r| {
+                        r.method(Method::GET).h(ota::node_sessions);
+                    })
+                    .resource("/ota/queue", |r| {
+                        r.method(Method::GET).h(ota::queue);
+                    })
+                    .resource("/ota/sessions", |r| {
+                        r.method(Method::GET).with(ota::list_sessions);
+                    })
+                    .resource("/campaigns", |r| {
+                        r.method(Method::GET).h(campaign::list);
+                        r.method(Method::POST).with(campaign::create);
+                    })
+                    .resource("/campaigns/{campaign_id}", |r| {
+                        r.method(Method::GET).h(campaign::get_campaign);
+                    })
+                    .resource("/campaigns/{campaign_id}/pause", |r| {
+                        r.method(Method::POST).h(campaign::pause);
+                    })
+                    .resource("/campaigns/{campaign_id}/resume", |r| {
+                        r.method(Method::POST).h(campaign::resume);
+                    })
+                    .resource("/campaigns/{campaign_id}/abort", |r| {
+                        r.method(Method::POST).h(campaign::abort);
+                    })
+                    .resource("/batteries/low", |r| {
+                        r.method(Method::GET).with(battery::list_low);
+                    })
+                    .resource("/inclusion", |r| {
+                        r.method(Method::GET).f(inclusion::get_state);
+                        r.method(Method::POST).with(inclusion::start);
+                        r.method(Method::DELETE).f(inclusion::stop);
+                    })
+                    .resource("/reservations", |r| {
+                        r.method(Method::GET).h(reservation::list);
+                        r.method(Method::POST).with(reservation::create);
+                    })
+                    .resource("/reservations/{node_id}", |r| {
+                        r.method(Method::DELETE).h(reservation::delete);
+                    })
                     .resource("/sensors", |r| {
                         r.method(Method::GET).h(sensor::list);
                         r.method(Method::DELETE).with(node::delete);
@@ -84,14 +156,37 @@ fn main() {
                     .resource("/sensors/{node_id}/{child_sensor_id}", |r| {
                         r.method(Method::GET).h(sensor::get_sensor);
                     })
+                    .resource("/sensors/{node_id}/{child_sensor_id}/values", |r| {
+                        r.method(Method::GET).with(sensor::get_values);
+                    })
+                    .resource("/topology", |r| {
+                        r.method(Method::GET).h(topology::get_topology);
+                    })
+                    .resource("/topology/dot", |r| {
+                        r.method(Method::GET).h(topology::export_dot);
+                    })
+                    .resource("/topology/graph", |r| {
+                        r.method(Method::GET).h(topology::export_graph);
+                    })
+                    .resource("/topology/discover", |r| {
+                        r.method(Method::POST).f(topology::discover);
+                    })
                     .resource("/firmwares", |r| {
-                        r.method(Method::GET).h(firmware::list);
+                        r.method(Method::GET).with(firmware::list);
+                        r.method(Method::POST).with(firmware::upload);
                     })
                     .resource("/firmwares/{firmware_type}/{firmware_version}", |r| {
+                        r.method(Method::GET).h(firmware::get);
                         r.method(Method::POST).with(firmware::create);
                         r.method(Method::PUT).with(firmware::update);
                         r.method(Method::DELETE).f(firmware::delete);
                     })
+                    .resource("/firmwares/{firmware_type}/{firmware_version}/nodes", |r| {
+                        r.method(Method::GET).h(firmware::nodes);
+                    })
+                    .resource("/firmwares/{firmware_type}/{firmware_version}/download", |r| {
+                        r.method(Method::GET).with(firmware::download);
+                    })
                     .resource("/firmwares/upload", |r| {
                         r.method(Method::GET).f(firmware::upload_form);
                     })
@@ -123,6 +218,13 @@ fn main() {
             in_set_sender,
             out_set_receiver,
             new_sensor_sender,
+            unit_system(&conf),
+            inclusion,
+            node_id_policy,
+            auto_rollback_after(&conf),
+            max_concurrent_ota(&conf),
+            ota_windows(&conf),
+            node_timeout_secs,
         );
     });
 
@@ -197,6 +299,115 @@ pub fn log_level(config: &Config) -> String {
     log_level.to_owned()
 }
 
+pub fn node_timeout(config: &Config) -> i64 {
+    let default_node_timeout = 3600;
+
+    let server_conf = match &config.Server {
+        Some(_config) => _config,
+        None => return default_node_timeout,
+    };
+
+    match &server_conf.node_timeout_secs {
+        Some(_node_timeout) => match _node_timeout.parse::<i64>() {
+            Ok(_node_timeout) if _node_timeout > 0 && _node_timeout <= MAX_TIMEOUT_SECS => _node_timeout,
+            _ => panic!(
+                "node_timeout_secs should be a number of seconds from 1 to {}. Ex:node_timeout_secs=\"3600\"",
+                MAX_TIMEOUT_SECS
+            ),
+        },
+        None => default_node_timeout,
+    }
+}
+
+pub fn unit_system(config: &Config) -> UnitSystem {
+    let server_conf = match &config.Server {
+        Some(_config) => _config,
+        None => return UnitSystem::Metric,
+    };
+
+    match &server_conf.unit_system {
+        Some(_unit_system) => match UnitSystem::from_name(_unit_system) {
+            Some(_unit_system) => _unit_system,
+            None => panic!("unit_system should be either metric or imperial. Ex:unit_system=\"metric\""),
+        },
+        None => UnitSystem::Metric,
+    }
+}
+
+pub fn inclusion_always_open(config: &Config) -> bool {
+    let server_conf = match &config.Server {
+        Some(_config) => _config,
+        None => return true,
+    };
+
+    match &server_conf.inclusion_always_open {
+        Some(_always_open) => match _always_open.parse::<bool>() {
+            Ok(_always_open) => _always_open,
+            Err(_) => panic!("inclusion_always_open should be either true or false. Ex:inclusion_always_open=\"false\""),
+        },
+        None => true,
+    }
+}
+
+pub fn node_id_policy(config: &Config) -> NodeIdPolicy {
+    let server_conf = match &config.Server {
+        Some(_config) => _config,
+        None => return NodeIdPolicy::default(),
+    };
+
+    let reserved = match &server_conf.reserved_node_ids {
+        Some(_reserved) => parse_ranges(_reserved).unwrap_or_else(|e| panic!("reserved_node_ids is invalid, {}. Ex:reserved_node_ids=\"200-220,230\"", e)),
+        None => Vec::new(),
+    };
+
+    let blocked = match &server_conf.blocked_node_ids {
+        Some(_blocked) => parse_ranges(_blocked).unwrap_or_else(|e| panic!("blocked_node_ids is invalid, {}. Ex:blocked_node_ids=\"250-254\"", e)),
+        None => Vec::new(),
+    };
+
+    let reuse_after_days = server_conf.node_id_reuse_after_days.as_ref().map(|_days| match _days.parse::<i64>() {
+        Ok(_days) if _days >= 0 && _days <= 3650 => _days,
+        _ => panic!("node_id_reuse_after_days should be a number of days from 0 to 3650. Ex:node_id_reuse_after_days=\"30\""),
+    });
+
+    NodeIdPolicy { reserved, blocked, reuse_after_days }
+}
+
+pub fn auto_rollback_after(config: &Config) -> Option<i64> {
+    let server_conf = match &config.Server {
+        Some(_config) => _config,
+        None => return None,
+    };
+
+    server_conf.auto_rollback_after_secs.as_ref().map(|_secs| match _secs.parse::<i64>() {
+        Ok(_secs) => _secs,
+        Err(_) => panic!("auto_rollback_after_secs should be a number of seconds. Ex:auto_rollback_after_secs=\"7200\""),
+    })
+}
+
+pub fn max_concurrent_ota(config: &Config) -> Option<usize> {
+    let server_conf = match &config.Server {
+        Some(_config) => _config,
+        None => return None,
+    };
+
+    server_conf.max_concurrent_ota.as_ref().map(|_max| match _max.parse::<usize>() {
+        Ok(_max) if _max > 0 => _max,
+        _ => panic!("max_concurrent_ota should be a number of nodes greater than 0. Ex:max_concurrent_ota=\"2\""),
+    })
+}
+
+pub fn ota_windows(config: &Config) -> Vec<MaintenanceWindow> {
+    let server_conf = match &config.Server {
+        Some(_config) => _config,
+        None => return Vec::new(),
+    };
+
+    match &server_conf.ota_window {
+        Some(_window) => parse_windows(_window).unwrap_or_else(|e| panic!("ota_window is invalid, {}. Ex:ota_window=\"02:00-05:00\"", e)),
+        None => Vec::new(),
+    }
+}
 
 fn get_mys_controller(config: &Config) -> Option<connection::ConnectionType> {
     let controller_conf = match &config.Controller {
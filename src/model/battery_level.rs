@@ -0,0 +1,26 @@
+use chrono::NaiveDateTime;
+
+table! {
+    battery_levels (id) {
+        id -> Integer,
+        node_id -> Integer,
+        level -> Integer,
+        reported_at -> Timestamp,
+    }
+}
+
+#[derive(Queryable, Serialize, Debug, PartialEq, Clone)]
+pub struct BatteryLevel {
+    pub id: i32,
+    pub node_id: i32,
+    pub level: i32,
+    pub reported_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "battery_levels"]
+pub struct NewBatteryLevel {
+    pub node_id: i32,
+    pub level: i32,
+    pub reported_at: NaiveDateTime,
+}
@@ -0,0 +1,68 @@
+use chrono::NaiveDateTime;
+
+table! {
+    use diesel::sql_types::Integer;
+    use diesel::sql_types::Nullable;
+    use diesel::sql_types::Timestamp;
+    use crate::model::campaign::CampaignStatusMapping;
+
+    campaigns (id) {
+        id -> Integer,
+        firmware_type -> Integer,
+        firmware_version -> Integer,
+        soak_minutes -> Integer,
+        status -> CampaignStatusMapping,
+        created_at -> Timestamp,
+        promoted_at -> Nullable<Timestamp>,
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    campaign_nodes (campaign_id, node_id) {
+        campaign_id -> Integer,
+        node_id -> Integer,
+        canary -> Bool,
+    }
+}
+
+#[derive(DbEnum, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CampaignStatus {
+    Running,
+    Paused,
+    Aborted,
+    Completed,
+}
+
+/// Staged rollout of a firmware, canary nodes are updated first and the remaining
+/// target nodes only once the canaries proved healthy for `soak_minutes`.
+#[derive(Queryable, Serialize, Debug, PartialEq, Clone)]
+pub struct Campaign {
+    pub id: i32,
+    pub firmware_type: i32,
+    pub firmware_version: i32,
+    pub soak_minutes: i32,
+    pub status: CampaignStatus,
+    pub created_at: NaiveDateTime,
+    pub promoted_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "campaigns"]
+pub struct NewCampaign {
+    pub firmware_type: i32,
+    pub firmware_version: i32,
+    pub soak_minutes: i32,
+    pub status: CampaignStatus,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Insertable, Serialize, Debug, PartialEq, Clone)]
+#[table_name = "campaign_nodes"]
+pub struct CampaignNode {
+    pub campaign_id: i32,
+    pub node_id: i32,
+    pub canary: bool,
+}
@@ -1,9 +1,42 @@
 use actix::*;
+use diesel::connection::SimpleConnection;
 use diesel::prelude::SqliteConnection;
-use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Error, Pool};
 
 pub struct ConnDsl(pub Pool<ConnectionManager<SqliteConnection>>);
 
 impl Actor for ConnDsl {
     type Context = SyncContext<Self>;
 }
+
+/// Lets the core threads and the api share the sqlite database, writers wait for the lock
+/// instead of failing with "database is locked".
+#[derive(Debug)]
+pub struct ConnectionOptions;
+
+impl CustomizeConnection<SqliteConnection, Error> for ConnectionOptions {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), Error> {
+        conn.batch_execute("PRAGMA journal_mode = WAL; PRAGMA busy_timeout = 5000;")
+            .map_err(Error::QueryError)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use diesel::prelude::SqliteConnection;
+    use diesel::r2d2::{ConnectionManager, Pool};
+
+    embed_migrations!("migrations");
+
+    /// Fresh in-memory database with the migrations run, a single connection
+    /// keeps it alive for the whole test.
+    pub fn pool() -> Pool<ConnectionManager<SqliteConnection>> {
+        let pool = Pool::builder()
+            .max_size(1)
+            .build(ConnectionManager::<SqliteConnection>::new(":memory:"))
+            .expect("Failed to create pool.");
+        let connection = pool.get().expect("Failed to get connection.");
+        embedded_migrations::run(&connection).expect("Failed to run migrations.");
+        pool
+    }
+}
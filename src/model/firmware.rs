@@ -1,16 +1,27 @@
 use std::fmt;
-use std::fs::File;
-use std::io::BufReader;
-use std::io::prelude::*;
+use std::fs;
 use std::iter::FromIterator;
+
+use chrono::NaiveDateTime;
 use std::path::Path;
 
 use crc16::*;
 use ihex::record::Record;
+use ihex::writer::{create_object_file_representation, WriterError};
+use sha2::{Digest, Sha256};
+
+use crate::model::firmware_file;
 
 pub const FIRMWARE_BLOCK_SIZE: i32 = 16;
 
 table! {
+    use diesel::sql_types::Binary;
+    use diesel::sql_types::Integer;
+    use diesel::sql_types::Nullable;
+    use diesel::sql_types::Text;
+    use diesel::sql_types::Timestamp;
+    use crate::model::firmware::BoardMapping;
+
     firmwares (firmware_type, firmware_version) {
         firmware_type -> Integer,
         firmware_version -> Integer,
@@ -18,6 +29,65 @@ table! {
         blocks -> Integer,
         crc -> Integer,
         data -> Binary,
+        board -> BoardMapping,
+        description -> Nullable<Text>,
+        uploaded_at -> Nullable<Timestamp>,
+        sha256 -> Nullable<Text>,
+        filename -> Nullable<Text>,
+        git_revision -> Nullable<Text>,
+    }
+}
+
+/// Target MCU of a firmware, firmwares are padded to its flash page size.
+/// Firmwares uploaded without a board are for the ATmega328.
+#[derive(DbEnum, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Board {
+    Atmega328,
+    Atmega1284p,
+    Atmega2560,
+}
+
+impl Board {
+    pub const ALL: [Board; 3] = [Board::Atmega328, Board::Atmega1284p, Board::Atmega2560];
+
+    pub fn page_size(self) -> usize {
+        match self {
+            Board::Atmega328 => 128,
+            Board::Atmega1284p | Board::Atmega2560 => 256,
+        }
+    }
+
+    /// Flash left for the application next to the MySensors bootloader.
+    pub fn flash_size(self) -> usize {
+        match self {
+            Board::Atmega328 => 32 * 1024 - 2 * 1024,
+            Board::Atmega1284p => 128 * 1024 - 4 * 1024,
+            Board::Atmega2560 => 256 * 1024 - 8 * 1024,
+        }
+    }
+
+    /// Pads the data with erased flash bytes up to a whole number of pages.
+    pub fn pad(self, data: &mut Vec<u8>) {
+        let remainder = data.len() % self.page_size();
+        if remainder != 0 {
+            let padded_len = data.len() + self.page_size() - remainder;
+            data.resize(padded_len, 255);
+        }
+    }
+
+    /// Checks the firmware fits in the flash of the board.
+    pub fn check_size(self, data: &[u8]) -> Result<(), String> {
+        if data.len() > self.flash_size() {
+            Err(format!(
+                "firmware of {} bytes doesn't fit in the {} bytes of flash of {:?}",
+                data.len(),
+                self.flash_size(),
+                self
+            ))
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -30,18 +100,34 @@ pub struct Firmware {
     pub blocks: i32,
     pub crc: i32,
     pub data: Vec<u8>,
+    pub board: Board,
+    pub description: Option<String>,
+    pub uploaded_at: Option<NaiveDateTime>,
+    /// SHA-256 of the uploaded file, before conversion to the stored data.
+    pub sha256: Option<String>,
+    /// Name of the uploaded file.
+    pub filename: Option<String>,
+    pub git_revision: Option<String>,
 }
 
 impl fmt::Debug for Firmware {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Firmware {{ name: {}, firmware_type: {}, firmware_version: {}, blocks: {}, crc: {} }}",
-            self.name, self.firmware_type, self.firmware_version, self.blocks, self.crc
+            "Firmware {{ name: {}, firmware_type: {}, firmware_version: {}, blocks: {}, crc: {}, board: {:?} }}",
+            self.name, self.firmware_type, self.firmware_version, self.blocks, self.crc, self.board
         )
     }
 }
 
+/// Flash image read from a firmware file, before padding.
+#[derive(Debug)]
+pub struct FirmwareImage {
+    pub data: Vec<u8>,
+    /// SHA-256 of the file the image was read from.
+    pub sha256: String,
+}
+
 #[derive(PartialEq, Eq, Hash, Debug)]
 pub struct FirmwareKey {
     pub _type: u16,
@@ -55,6 +141,7 @@ impl Firmware {
         blocks: i32,
         data: Vec<u8>,
         name: String,
+        board: Board,
     ) -> Firmware {
         Firmware {
             firmware_type,
@@ -63,12 +150,18 @@ impl Firmware {
             blocks,
             crc: i32::from(Firmware::compute_crc(&data)),
             data,
+            board,
+            description: None,
+            uploaded_at: None,
+            sha256: None,
+            filename: None,
+            git_revision: None,
         }
     }
 
     pub fn get_block(&self, block: u16) -> [u8; 16] {
-        let start_index: usize = (block * 16) as usize;
-        if start_index > self.data.len() {
+        let start_index: usize = usize::from(block) * 16;
+        if start_index >= self.data.len() {
             let no_binary: [u8; 16] = [0; 16];
             return no_binary;
         }
@@ -94,27 +187,47 @@ impl Firmware {
         }
     }
 
-    pub fn prepare_fw(_type: i32, version: i32, name: String, path: &Path) -> Option<Firmware> {
-        match File::open(path) {
-            Ok(f) => {
-                let f = BufReader::new(f);
-                let mut data: Vec<u8> = f.lines()
-                    .flat_map(|line| {
-                        Firmware::ihex_to_bin(&Record::from_record_string(&line.unwrap()).unwrap())
-                    })
-                    .collect();
-                let pads: usize = data.len() % 128; // 128 bytes per page for atmega328
-                for _ in 0..(128 - pads) {
-                    data.push(255);
-                }
-                let blocks: i32 = data.len() as i32 / FIRMWARE_BLOCK_SIZE;
-                Some(Firmware::new(_type, version, blocks, data, name))
-            }
-            Err(_) => {
-                error!("Error opening file {:?}", path);
-                None
+    /// Reads an Intel HEX, ELF or raw binary firmware file for the board.
+    pub fn prepare_fw(board: Board, path: &Path) -> Result<FirmwareImage, String> {
+        let content = fs::read(path).map_err(|e| {
+            error!("Error opening file {:?}", path);
+            format!("can't read firmware file, {}", e)
+        })?;
+        Ok(FirmwareImage {
+            data: firmware_file::read_image(&content, board.flash_size())?,
+            sha256: hex::encode(Sha256::digest(&content)),
+        })
+    }
+
+    /// Pads the data to the page size of the board, failing when it doesn't fit in its flash.
+    pub fn build(
+        firmware_type: i32,
+        firmware_version: i32,
+        name: String,
+        board: Board,
+        mut data: Vec<u8>,
+    ) -> Result<Firmware, String> {
+        board.pad(&mut data);
+        board.check_size(&data)?;
+        let blocks: i32 = data.len() as i32 / FIRMWARE_BLOCK_SIZE;
+        Ok(Firmware::new(firmware_type, firmware_version, blocks, data, name, board))
+    }
+
+    /// Intel HEX file of the stored data, starting at address 0 and including the page padding.
+    pub fn to_ihex(&self) -> Result<String, WriterError> {
+        let mut records = Vec::new();
+        for (index, chunk) in self.data.chunks(FIRMWARE_BLOCK_SIZE as usize).enumerate() {
+            let address = index * FIRMWARE_BLOCK_SIZE as usize;
+            if address > 0 && address & 0xFFFF == 0 {
+                records.push(Record::ExtendedLinearAddress((address >> 16) as u16));
             }
+            records.push(Record::Data {
+                offset: (address & 0xFFFF) as u16,
+                value: chunk.to_vec(),
+            });
         }
+        records.push(Record::EndOfFile);
+        create_object_file_representation(&records)
     }
 
     pub fn compute_crc(data: &[u8]) -> u16 {
@@ -144,39 +257,91 @@ mod test {
         );
     }
 
+    fn blink() -> Firmware {
+        let image = Firmware::prepare_fw(Board::Atmega328, &PathBuf::from("firmwares/10__2__Blink.ino.hex")).unwrap();
+        Firmware::build(10, 2, String::from("Blink"), Board::Atmega328, image.data).unwrap()
+    }
+
     #[test]
     fn hex_file_to_vector() {
-        let fw_binary = Firmware::prepare_fw(
-            10,
-            2,
-            String::from("Blink"),
-            &PathBuf::from("firmwares/10__2__Blink.ino.hex"),
-        ).unwrap();
+        let image = Firmware::prepare_fw(Board::Atmega328, &PathBuf::from("firmwares/10__2__Blink.ino.hex")).unwrap();
+        assert_eq!(
+            "c7e78eed99f82eae00638a933ed84bd1c0bd0ec70fa880a38e1a11000db00026",
+            image.sha256
+        );
+        let fw_binary = Firmware::build(10, 2, String::from("Blink"), Board::Atmega328, image.data).unwrap();
         assert_eq!(fw_binary.data.len(), 1280);
+        assert_eq!(fw_binary.blocks, 80);
     }
 
     #[test]
     fn extract_given_block_from_binary_data() {
-        let fw_binary = Firmware::prepare_fw(
-            10,
-            2,
-            String::from("Blink"),
-            &PathBuf::from("firmwares/10__2__Blink.ino.hex"),
-        ).unwrap();
+        let fw_binary = blink();
         assert_eq!(
             fw_binary.get_block(1),
             [12, 148, 110, 0, 12, 148, 110, 0, 12, 148, 110, 0, 12, 148, 110, 0, ]
         );
     }
 
+    #[test]
+    fn stored_data_to_ihex() {
+        let fw_binary = blink();
+        let ihex = fw_binary.to_ihex().unwrap();
+        let lines: Vec<&str> = ihex.lines().collect();
+        assert_eq!(":100000000C945C000C946E000C946E000C946E00CA", lines[0]);
+        assert_eq!(":00000001FF", lines[lines.len() - 1]);
+        let data: Vec<u8> = lines
+            .iter()
+            .flat_map(|line| Firmware::ihex_to_bin(&Record::from_record_string(line).unwrap()))
+            .collect();
+        assert_eq!(fw_binary.data, data);
+    }
+
+    #[test]
+    fn ihex_of_large_firmware_has_extended_addresses() {
+        let firmware = Firmware::new(10, 2, 4097, vec![0; 0x10010], String::from("Large"), Board::Atmega2560);
+        let ihex = firmware.to_ihex().unwrap();
+        let lines: Vec<&str> = ihex.lines().collect();
+        assert_eq!(":020000040001F9", lines[4096]);
+        assert_eq!(":1000000000000000000000000000000000000000F0", lines[4097]);
+    }
+
+    #[test]
+    fn pad_to_board_page_size() {
+        let mut data = vec![0; 130];
+        Board::Atmega328.pad(&mut data);
+        assert_eq!(256, data.len());
+        assert_eq!(255, data[255]);
+        Board::Atmega328.pad(&mut data);
+        assert_eq!(256, data.len());
+        let mut data = vec![0; 130];
+        Board::Atmega2560.pad(&mut data);
+        assert_eq!(256, data.len());
+        let mut data = vec![0; 257];
+        Board::Atmega1284p.pad(&mut data);
+        assert_eq!(512, data.len());
+    }
+
+    #[test]
+    fn firmware_must_fit_in_board_flash() {
+        assert!(Board::Atmega328.check_size(&vec![0; 30 * 1024]).is_ok());
+        assert!(Board::Atmega328.check_size(&vec![0; 30 * 1024 + 1]).is_err());
+        assert!(Board::Atmega2560.check_size(&vec![0; 30 * 1024 + 1]).is_ok());
+        assert!(Firmware::build(10, 2, String::from("Large"), Board::Atmega328, vec![0; 30 * 1024 + 1]).is_err());
+    }
+
+    #[test]
+    fn get_blocks_beyond_64k() {
+        let mut data = vec![0; 0x10000];
+        data.extend_from_slice(&[1; 16]);
+        let firmware = Firmware::new(10, 2, 4097, data, String::from("Large"), Board::Atmega2560);
+        assert_eq!([1; 16], firmware.get_block(4096));
+        assert_eq!([0; 16], firmware.get_block(4097));
+    }
+
     #[test]
     fn compute_correct_crc() {
-        let fw_binary = Firmware::prepare_fw(
-            10,
-            2,
-            String::from("Blink"),
-            &PathBuf::from("firmwares/10__2__Blink.ino.hex"),
-        ).unwrap();
+        let fw_binary = blink();
         assert_eq!(Firmware::compute_crc(&fw_binary.data), 0x46D4);
     }
 }
@@ -0,0 +1,215 @@
+use goblin::elf::program_header::PT_LOAD;
+use goblin::elf::Elf;
+use ihex::record::Record;
+
+/// SRAM and EEPROM contents of AVR ELF files are loaded at 0x800000 and above.
+const AVR_DATA_SPACE: u64 = 0x80_0000;
+const ERASED_FLASH: u8 = 255;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FirmwareFormat {
+    Hex,
+    Bin,
+    Elf,
+}
+
+impl FirmwareFormat {
+    /// Anything that is neither an ELF nor an Intel HEX file is taken as a raw binary.
+    pub fn detect(content: &[u8]) -> FirmwareFormat {
+        if content.starts_with(b"\x7FELF") {
+            FirmwareFormat::Elf
+        } else if content.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b':')
+            && std::str::from_utf8(content).is_ok()
+        {
+            FirmwareFormat::Hex
+        } else {
+            FirmwareFormat::Bin
+        }
+    }
+}
+
+/// Flash image of a firmware file starting at address 0, gaps are left as erased flash.
+pub fn read_image(content: &[u8], max_size: usize) -> Result<Vec<u8>, String> {
+    let image = match FirmwareFormat::detect(content) {
+        FirmwareFormat::Hex => hex_image(content, max_size)?,
+        FirmwareFormat::Elf => elf_image(content, max_size)?,
+        FirmwareFormat::Bin => {
+            let mut image = Vec::new();
+            write(&mut image, 0, content, max_size)?;
+            image
+        }
+    };
+    if image.is_empty() {
+        return Err("firmware file has no data".to_owned());
+    }
+    Ok(image)
+}
+
+fn hex_image(content: &[u8], max_size: usize) -> Result<Vec<u8>, String> {
+    let text = std::str::from_utf8(content).map_err(|_| "intel hex file is not valid text".to_owned())?;
+    let mut image = Vec::new();
+    let mut base_address = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let record = Record::from_record_string(line)
+            .map_err(|e| format!("invalid intel hex record on line {}, {:?}", index + 1, e))?;
+        match record {
+            Record::Data { offset, value } => write(&mut image, base_address + usize::from(offset), &value, max_size)?,
+            Record::ExtendedSegmentAddress(segment) => base_address = usize::from(segment) << 4,
+            Record::ExtendedLinearAddress(upper) => base_address = usize::from(upper) << 16,
+            Record::EndOfFile => break,
+            Record::StartSegmentAddress { .. } | Record::StartLinearAddress(_) => (),
+        }
+    }
+    Ok(image)
+}
+
+fn elf_image(content: &[u8], max_size: usize) -> Result<Vec<u8>, String> {
+    let elf = Elf::parse(content).map_err(|e| format!("invalid elf file, {}", e))?;
+    let mut image = Vec::new();
+    for header in elf
+        .program_headers
+        .iter()
+        .filter(|header| header.p_type == PT_LOAD && header.p_filesz > 0 && header.p_paddr < AVR_DATA_SPACE)
+    {
+        let start = header.p_offset as usize;
+        let segment = start
+            .checked_add(header.p_filesz as usize)
+            .and_then(|end| content.get(start..end))
+            .ok_or_else(|| "elf segment is beyond the end of the file".to_owned())?;
+        write(&mut image, header.p_paddr as usize, segment, max_size)?;
+    }
+    Ok(image)
+}
+
+fn write(image: &mut Vec<u8>, address: usize, data: &[u8], max_size: usize) -> Result<(), String> {
+    let end = match address.checked_add(data.len()) {
+        Some(end) if end <= max_size => end,
+        _ => {
+            return Err(format!(
+                "data at address 0x{:X} doesn't fit in the {} bytes of flash",
+                address, max_size
+            ))
+        }
+    };
+    if image.len() < end {
+        image.resize(end, ERASED_FLASH);
+    }
+    image[address..end].copy_from_slice(data);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// ELF32 file with one program header per (type, physical address, data) segment.
+    fn elf(segments: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let mut elf = b"\x7FELF\x01\x01\x01".to_vec();
+        elf.resize(16, 0);
+        for value in &[2u16, 83] {
+            elf.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in &[1u32, 0, 52, 0, 0] {
+            elf.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in &[52u16, 32, segments.len() as u16, 40, 0, 0] {
+            elf.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut offset = 52 + 32 * segments.len() as u32;
+        for (p_type, address, data) in segments {
+            let size = data.len() as u32;
+            for value in &[*p_type, offset, *address, *address, size, size, 5, 1] {
+                elf.extend_from_slice(&value.to_le_bytes());
+            }
+            offset += size;
+        }
+        for (_, _, data) in segments {
+            elf.extend_from_slice(data);
+        }
+        elf
+    }
+
+    #[test]
+    fn detect_firmware_format() {
+        assert_eq!(FirmwareFormat::Hex, FirmwareFormat::detect(b"\r\n:00000001FF\r\n"));
+        assert_eq!(FirmwareFormat::Elf, FirmwareFormat::detect(&elf(&[])));
+        assert_eq!(FirmwareFormat::Bin, FirmwareFormat::detect(&[0x0C, 0x94, 0x5C, 0x00]));
+    }
+
+    #[test]
+    fn hex_image_honours_addresses() {
+        let hex = ":020000000102FB\n\
+                   :02000400AABB95\n\
+                   :020000021000EC\n\
+                   :02000200CCDD53\n\
+                   :00000001FF\n\
+                   :02000000EEEE22\n";
+        let image = read_image(hex.as_bytes(), 0x20000).unwrap();
+        assert_eq!(0x10004, image.len());
+        assert_eq!(&[1, 2, 255, 255, 0xAA, 0xBB, 255], &image[0..7]);
+        assert_eq!(&[255, 255, 0xCC, 0xDD], &image[0x10000..]);
+    }
+
+    #[test]
+    fn hex_image_with_extended_linear_address() {
+        let hex = ":020000040001F9\n:0200000001FFFE\n:00000001FF\n";
+        let image = read_image(hex.as_bytes(), 0x20000).unwrap();
+        assert_eq!(0x10002, image.len());
+        assert_eq!(&[1, 255], &image[0x10000..]);
+    }
+
+    #[test]
+    fn invalid_hex_record_is_rejected() {
+        assert!(read_image(b":020000000102FF\n", 0x8000).is_err());
+    }
+
+    #[test]
+    fn raw_binary_is_used_as_is() {
+        assert_eq!(Ok(vec![0x0C, 0x94, 0x5C, 0x00]), read_image(&[0x0C, 0x94, 0x5C, 0x00], 0x8000));
+        assert!(read_image(&[0; 16], 8).is_err());
+        assert!(read_image(&[], 8).is_err());
+    }
+
+    #[test]
+    fn elf_image_from_loadable_segments() {
+        let elf = elf(&[
+            (PT_LOAD, 0, &[1, 2, 3, 4]),
+            (4, 0x10, &[9, 9]),
+            (PT_LOAD, 0x6, &[5, 6]),
+            (PT_LOAD, 0x81_0000, &[7, 7]),
+        ]);
+        assert_eq!(Ok(vec![1, 2, 3, 4, 255, 255, 5, 6]), read_image(&elf, 0x8000));
+    }
+
+    #[test]
+    fn elf_beyond_flash_is_rejected() {
+        assert!(read_image(&elf(&[(PT_LOAD, 0x7FFF, &[1, 2])]), 0x8000).is_err());
+    }
+
+    #[test]
+    fn elf64_segment_with_overflowing_size_is_rejected() {
+        let mut elf = b"\x7FELF\x02\x01\x01".to_vec();
+        elf.resize(16, 0);
+        for value in &[2u16, 83] {
+            elf.extend_from_slice(&value.to_le_bytes());
+        }
+        elf.extend_from_slice(&1u32.to_le_bytes());
+        for value in &[0u64, 64, 0] {
+            elf.extend_from_slice(&value.to_le_bytes());
+        }
+        elf.extend_from_slice(&0u32.to_le_bytes());
+        for value in &[64u16, 56, 1, 64, 0, 0] {
+            elf.extend_from_slice(&value.to_le_bytes());
+        }
+        elf.extend_from_slice(&PT_LOAD.to_le_bytes());
+        elf.extend_from_slice(&5u32.to_le_bytes());
+        for value in &[8u64, 0, 0, u64::max_value(), u64::max_value(), 1] {
+            elf.extend_from_slice(&value.to_le_bytes());
+        }
+        assert!(read_image(&elf, 0x8000).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::NaiveDateTime;
+
+table! {
+    firmware_history (id) {
+        id -> Integer,
+        node_id -> Integer,
+        firmware_type -> Integer,
+        firmware_version -> Integer,
+        observed_at -> Timestamp,
+        bad -> Bool,
+    }
+}
+
+/// Firmware a node was seen running, `bad` once the node was rolled back from it.
+#[derive(Queryable, Serialize, Debug, PartialEq, Clone)]
+pub struct FirmwareHistory {
+    pub id: i32,
+    pub node_id: i32,
+    pub firmware_type: i32,
+    pub firmware_version: i32,
+    pub observed_at: NaiveDateTime,
+    pub bad: bool,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "firmware_history"]
+pub struct NewFirmwareHistory {
+    pub node_id: i32,
+    pub firmware_type: i32,
+    pub firmware_version: i32,
+    pub observed_at: NaiveDateTime,
+}
+
+/// Latest firmware other than the current one the node ran without being rolled back from,
+/// `history` is ordered from the newest entry.
+pub fn previous_good(history: &[FirmwareHistory], current: (i32, i32)) -> Option<&FirmwareHistory> {
+    history
+        .iter()
+        .filter(|entry| (entry.firmware_type, entry.firmware_version) != current)
+        .find(|entry| !entry.bad)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(firmware_version: i32, bad: bool) -> FirmwareHistory {
+        FirmwareHistory {
+            id: firmware_version,
+            node_id: 1,
+            firmware_type: 10,
+            firmware_version,
+            observed_at: NaiveDateTime::from_timestamp(i64::from(firmware_version), 0),
+            bad,
+        }
+    }
+
+    #[test]
+    fn previous_good_skips_current_and_bad_firmwares() {
+        let history = vec![entry(4, false), entry(3, true), entry(2, false), entry(1, false)];
+        assert_eq!(Some(&history[2]), previous_good(&history, (10, 4)));
+        assert_eq!(Some(&history[0]), previous_good(&history, (10, 3)));
+        assert_eq!(None, previous_good(&history[..1], (10, 4)));
+    }
+}
@@ -0,0 +1,28 @@
+use chrono::NaiveDateTime;
+
+use crate::core::message::set::SetReqType;
+
+table! {
+    use diesel::sql_types::Integer;
+    use diesel::sql_types::Text;
+    use diesel::sql_types::Timestamp;
+    use crate::core::message::set::SetReqTypeMapping;
+
+    last_values (node_id, child_sensor_id, set_type) {
+        node_id -> Integer,
+        child_sensor_id -> Integer,
+        set_type -> SetReqTypeMapping,
+        value -> Text,
+        updated_at -> Timestamp,
+    }
+}
+
+#[derive(Queryable, Insertable, Debug, PartialEq, Clone)]
+#[table_name = "last_values"]
+pub struct LastValue {
+    pub node_id: i32,
+    pub child_sensor_id: i32,
+    pub set_type: SetReqType,
+    pub value: String,
+    pub updated_at: NaiveDateTime,
+}
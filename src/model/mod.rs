@@ -1,4 +1,14 @@
+pub mod battery_level;
+pub mod campaign;
 pub mod db;
 pub mod firmware;
+pub mod firmware_file;
+pub mod firmware_history;
+pub mod last_value;
 pub mod node;
+pub mod node_id_reservation;
+pub mod node_ping;
+pub mod ota_session;
 pub mod sensor;
+pub mod sensor_value;
+pub mod topology;
@@ -1,4 +1,18 @@
+use chrono::{Duration, NaiveDateTime};
+
+use crate::core::message::internal::UnitSystem;
+
+/// Longest timeout after which a node is reported offline, a year.
+pub const MAX_TIMEOUT_SECS: i64 = 365 * 24 * 3600;
+
 table! {
+    use diesel::sql_types::Bool;
+    use diesel::sql_types::Integer;
+    use diesel::sql_types::Nullable;
+    use diesel::sql_types::Text;
+    use diesel::sql_types::Timestamp;
+    use crate::core::message::internal::UnitSystemMapping;
+
     nodes (node_id) {
         node_id -> Integer,
         node_name -> Text,
@@ -9,10 +23,20 @@ table! {
         auto_update -> Bool,
         scheduled -> Bool,
         parent_node_id -> Integer,
+        last_seen -> Nullable<Timestamp>,
+        timeout_secs -> Nullable<Integer>,
+        battery_level -> Nullable<Integer>,
+        sketch_name -> Nullable<Text>,
+        sketch_version -> Nullable<Text>,
+        library_version -> Nullable<Text>,
+        is_repeater -> Bool,
+        unit_system -> Nullable<UnitSystemMapping>,
+        ota_window -> Nullable<Text>,
+        last_boot_at -> Nullable<Timestamp>,
     }
 }
 
-#[derive(Queryable, Serialize, Deserialize, Insertable, Debug)]
+#[derive(Queryable, Serialize, Deserialize, Insertable, Debug, Default)]
 #[table_name = "nodes"]
 pub struct Node {
     pub node_id: i32,
@@ -24,10 +48,70 @@ pub struct Node {
     pub auto_update: bool,
     pub scheduled: bool,
     pub parent_node_id: i32,
+    pub last_seen: Option<NaiveDateTime>,
+    pub timeout_secs: Option<i32>,
+    pub battery_level: Option<i32>,
+    pub sketch_name: Option<String>,
+    pub sketch_version: Option<String>,
+    pub library_version: Option<String>,
+    pub is_repeater: bool,
+    pub unit_system: Option<UnitSystem>,
+    /// Maintenance windows like "02:00-05:00" overriding the installation ones.
+    pub ota_window: Option<String>,
+    /// When the node last asked for its firmware config, which it does on every boot.
+    pub last_boot_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeStatus {
+    Online,
+    Offline,
 }
 
 impl Node {
     pub fn node_id(&self) -> u8 {
         self.node_id as u8
     }
+
+    /// A node is online when it has been heard from within its own timeout,
+    /// or within `default_timeout_secs` when the node doesn't have one.
+    pub fn status(&self, default_timeout_secs: i64, now: NaiveDateTime) -> NodeStatus {
+        match self.last_seen {
+            Some(last_seen) if now - last_seen <= self.timeout(default_timeout_secs) => NodeStatus::Online,
+            _ => NodeStatus::Offline,
+        }
+    }
+
+    pub fn timeout(&self, default_timeout_secs: i64) -> Duration {
+        Duration::seconds(self.timeout_secs.map(i64::from).unwrap_or(default_timeout_secs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn node(last_seen: Option<NaiveDateTime>, timeout_secs: Option<i32>) -> Node {
+        Node {
+            node_id: 1,
+            node_name: "New Node".to_owned(),
+            last_seen,
+            timeout_secs,
+            ..Node::default()
+        }
+    }
+
+    #[test]
+    fn node_status_from_last_seen() {
+        let now = NaiveDateTime::from_timestamp(1_550_000_000, 0);
+        let a_minute_ago = Some(now - Duration::minutes(1));
+        let an_hour_ago = Some(now - Duration::hours(1));
+
+        assert_eq!(node(a_minute_ago, None).status(600, now), NodeStatus::Online);
+        assert_eq!(node(an_hour_ago, None).status(600, now), NodeStatus::Offline);
+        assert_eq!(node(an_hour_ago, Some(7200)).status(600, now), NodeStatus::Online);
+        assert_eq!(node(a_minute_ago, Some(30)).status(600, now), NodeStatus::Offline);
+        assert_eq!(node(None, None).status(600, now), NodeStatus::Offline);
+    }
 }
@@ -0,0 +1,31 @@
+use chrono::NaiveDateTime;
+
+table! {
+    node_id_reservations (node_id) {
+        node_id -> Integer,
+        note -> Nullable<Text>,
+        reserved_at -> Timestamp,
+    }
+}
+
+table! {
+    deleted_nodes (node_id) {
+        node_id -> Integer,
+        deleted_at -> Timestamp,
+    }
+}
+
+#[derive(Queryable, Insertable, Serialize, Debug, PartialEq, Clone)]
+#[table_name = "node_id_reservations"]
+pub struct NodeIdReservation {
+    pub node_id: i32,
+    pub note: Option<String>,
+    pub reserved_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Insertable, Serialize, Debug, PartialEq, Clone)]
+#[table_name = "deleted_nodes"]
+pub struct DeletedNode {
+    pub node_id: i32,
+    pub deleted_at: NaiveDateTime,
+}
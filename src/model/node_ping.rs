@@ -0,0 +1,30 @@
+use chrono::NaiveDateTime;
+
+table! {
+    node_pings (id) {
+        id -> Integer,
+        node_id -> Integer,
+        sent_at -> Timestamp,
+        received_at -> Nullable<Timestamp>,
+        hops -> Nullable<Integer>,
+        round_trip_ms -> Nullable<Integer>,
+    }
+}
+
+/// A ping sent to a node, the pong fields stay empty until the node answers.
+#[derive(Queryable, Serialize, Debug, PartialEq, Clone)]
+pub struct NodePing {
+    pub id: i32,
+    pub node_id: i32,
+    pub sent_at: NaiveDateTime,
+    pub received_at: Option<NaiveDateTime>,
+    pub hops: Option<i32>,
+    pub round_trip_ms: Option<i32>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "node_pings"]
+pub struct NewNodePing {
+    pub node_id: i32,
+    pub sent_at: NaiveDateTime,
+}
@@ -0,0 +1,126 @@
+use chrono::NaiveDateTime;
+
+table! {
+    use diesel::sql_types::Integer;
+    use diesel::sql_types::Nullable;
+    use diesel::sql_types::Timestamp;
+    use crate::model::ota_session::OtaStatusMapping;
+
+    ota_sessions (id) {
+        id -> Integer,
+        node_id -> Integer,
+        firmware_type -> Integer,
+        firmware_version -> Integer,
+        total_blocks -> Integer,
+        blocks_requested -> Integer,
+        highest_block -> Nullable<Integer>,
+        lowest_block -> Nullable<Integer>,
+        started_at -> Timestamp,
+        last_block_at -> Nullable<Timestamp>,
+        status -> OtaStatusMapping,
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    ota_queue (node_id) {
+        node_id -> Integer,
+        firmware_type -> Integer,
+        firmware_version -> Integer,
+        queued_at -> Timestamp,
+    }
+}
+
+#[derive(DbEnum, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OtaStatus {
+    InProgress,
+    /// All blocks were sent, waiting for the node to reboot into the new firmware.
+    Transferred,
+    Completed,
+    Failed,
+}
+
+/// Firmware transfer to a node, blocks are requested from the last one down to 0.
+#[derive(Queryable, Serialize, Debug, PartialEq, Clone)]
+pub struct OtaSession {
+    pub id: i32,
+    pub node_id: i32,
+    pub firmware_type: i32,
+    pub firmware_version: i32,
+    pub total_blocks: i32,
+    pub blocks_requested: i32,
+    pub highest_block: Option<i32>,
+    pub lowest_block: Option<i32>,
+    pub started_at: NaiveDateTime,
+    pub last_block_at: Option<NaiveDateTime>,
+    pub status: OtaStatus,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "ota_sessions"]
+pub struct NewOtaSession {
+    pub node_id: i32,
+    pub firmware_type: i32,
+    pub firmware_version: i32,
+    pub total_blocks: i32,
+    pub started_at: NaiveDateTime,
+    pub status: OtaStatus,
+}
+
+/// Node waiting for a free ota slot, it keeps running its current firmware meanwhile.
+#[derive(Queryable, Insertable, Serialize, Debug, PartialEq, Clone)]
+#[table_name = "ota_queue"]
+pub struct QueuedOta {
+    pub node_id: i32,
+    pub firmware_type: i32,
+    pub firmware_version: i32,
+    pub queued_at: NaiveDateTime,
+}
+
+impl OtaSession {
+    /// Percentage of the firmware already sent to the node.
+    pub fn progress(&self) -> f64 {
+        match self.lowest_block {
+            Some(_) if self.status == OtaStatus::Transferred || self.status == OtaStatus::Completed => {
+                100.0
+            }
+            Some(lowest_block) if self.total_blocks > 0 => {
+                f64::from(self.total_blocks - lowest_block) * 100.0 / f64::from(self.total_blocks)
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn session(lowest_block: Option<i32>, status: OtaStatus) -> OtaSession {
+        OtaSession {
+            id: 1,
+            node_id: 1,
+            firmware_type: 10,
+            firmware_version: 2,
+            total_blocks: 200,
+            blocks_requested: 0,
+            highest_block: lowest_block.map(|_| 199),
+            lowest_block,
+            started_at: NaiveDateTime::from_timestamp(0, 0),
+            last_block_at: None,
+            status,
+            finished_at: None,
+        }
+    }
+
+    #[test]
+    fn progress_follows_lowest_block_served() {
+        assert_eq!(0.0, session(None, OtaStatus::InProgress).progress());
+        assert_eq!(0.5, session(Some(199), OtaStatus::InProgress).progress());
+        assert_eq!(50.0, session(Some(100), OtaStatus::InProgress).progress());
+        assert_eq!(100.0, session(Some(0), OtaStatus::Transferred).progress());
+        assert_eq!(100.0, session(Some(0), OtaStatus::Completed).progress());
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDateTime;
+
+use crate::core::message::set::SetReqType;
+
+table! {
+    use diesel::sql_types::Double;
+    use diesel::sql_types::Integer;
+    use diesel::sql_types::Nullable;
+    use diesel::sql_types::Text;
+    use diesel::sql_types::Timestamp;
+    use crate::core::message::set::SetReqTypeMapping;
+
+    sensor_values (id) {
+        id -> Integer,
+        node_id -> Integer,
+        child_sensor_id -> Integer,
+        set_type -> SetReqTypeMapping,
+        value -> Text,
+        numeric_value -> Nullable<Double>,
+        received_at -> Timestamp,
+    }
+}
+
+#[derive(Queryable, Serialize, Debug, PartialEq, Clone)]
+pub struct SensorValue {
+    pub id: i32,
+    pub node_id: i32,
+    pub child_sensor_id: i32,
+    pub set_type: SetReqType,
+    pub value: String,
+    pub numeric_value: Option<f64>,
+    pub received_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "sensor_values"]
+pub struct NewSensorValue {
+    pub node_id: i32,
+    pub child_sensor_id: i32,
+    pub set_type: SetReqType,
+    pub value: String,
+    pub numeric_value: Option<f64>,
+    pub received_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AggregatedValue {
+    pub set_type: SetReqType,
+    pub bucket_start: NaiveDateTime,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub count: usize,
+}
+
+/// Groups the numeric values into buckets of `bucket_secs` seconds, aligned to the unix epoch.
+/// Values without a numeric representation are left out.
+pub fn aggregate(values: &[SensorValue], bucket_secs: i64) -> Vec<AggregatedValue> {
+    let mut buckets: BTreeMap<(i64, u8), (SetReqType, Vec<f64>)> = BTreeMap::new();
+    for sensor_value in values {
+        if let Some(number) = sensor_value.numeric_value {
+            let bucket = sensor_value.received_at.timestamp().div_euclid(bucket_secs);
+            buckets
+                .entry((bucket, sensor_value.set_type as u8))
+                .or_insert_with(|| (sensor_value.set_type, Vec::new()))
+                .1
+                .push(number);
+        }
+    }
+    buckets
+        .into_iter()
+        .map(|((bucket, _), (set_type, numbers))| AggregatedValue {
+            set_type,
+            bucket_start: NaiveDateTime::from_timestamp(bucket * bucket_secs, 0),
+            min: numbers.iter().cloned().fold(f64::INFINITY, f64::min),
+            max: numbers.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            avg: numbers.iter().sum::<f64>() / numbers.len() as f64,
+            count: numbers.len(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn sensor_value(set_type: SetReqType, value: &str, received_at: &str) -> SensorValue {
+        SensorValue {
+            id: 0,
+            node_id: 1,
+            child_sensor_id: 1,
+            set_type,
+            value: value.to_owned(),
+            numeric_value: value.parse::<f64>().ok(),
+            received_at: NaiveDateTime::parse_from_str(received_at, "%Y-%m-%dT%H:%M:%S").unwrap(),
+        }
+    }
+
+    #[test]
+    fn aggregate_values_per_bucket_and_type() {
+        let from = NaiveDateTime::parse_from_str("2019-01-01T00:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+        let values = vec![
+            sensor_value(SetReqType::Temp, "20", "2019-01-01T00:10:00"),
+            sensor_value(SetReqType::Temp, "22", "2019-01-01T00:50:00"),
+            sensor_value(SetReqType::Hum, "60", "2019-01-01T00:20:00"),
+            sensor_value(SetReqType::Temp, "25", "2019-01-01T01:05:00"),
+            sensor_value(SetReqType::Text, "ignored", "2019-01-01T01:06:00"),
+        ];
+
+        let aggregated = aggregate(&values, 3600);
+
+        assert_eq!(aggregated.len(), 3);
+        assert_eq!(aggregated[0].set_type, SetReqType::Temp);
+        assert_eq!(aggregated[0].bucket_start, from);
+        assert_eq!(aggregated[0].min, 20.0);
+        assert_eq!(aggregated[0].max, 22.0);
+        assert_eq!(aggregated[0].avg, 21.0);
+        assert_eq!(aggregated[0].count, 2);
+        assert_eq!(aggregated[1].set_type, SetReqType::Hum);
+        assert_eq!(aggregated[1].count, 1);
+        assert_eq!(aggregated[2].bucket_start, from + Duration::hours(1));
+        assert_eq!(aggregated[2].avg, 25.0);
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use chrono::NaiveDateTime;
+
+use crate::model::node::Node;
+
+pub const GATEWAY_NODE_ID: i32 = 0;
+
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Gateway,
+    Repeater,
+    Node,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TopologyNode {
+    pub node_id: i32,
+    pub node_name: String,
+    pub kind: NodeKind,
+    pub parent_node_id: Option<i32>,
+    /// Number of radio hops to the gateway, missing when the node can't reach it.
+    pub hops: Option<u32>,
+    pub children: Vec<TopologyNode>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Topology {
+    pub gateway: TopologyNode,
+    /// Nodes whose parent chain doesn't lead to the gateway.
+    pub unreachable: Vec<TopologyNode>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GraphNode {
+    pub id: i32,
+    pub name: String,
+    pub kind: NodeKind,
+    pub hops: Option<u32>,
+    pub battery_level: Option<i32>,
+    pub last_seen: Option<NaiveDateTime>,
+}
+
+/// Radio link from a node to its parent.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GraphEdge {
+    pub source: i32,
+    pub target: i32,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl Topology {
+    /// Hop count of every node reaching the gateway, including the gateway itself.
+    pub fn hops(&self) -> HashMap<i32, u32> {
+        let mut hops = HashMap::new();
+        collect_hops(&self.gateway, &mut hops);
+        hops
+    }
+}
+
+fn collect_hops(topology_node: &TopologyNode, hops: &mut HashMap<i32, u32>) {
+    if let Some(node_hops) = topology_node.hops {
+        hops.insert(topology_node.node_id, node_hops);
+    }
+    for child in &topology_node.children {
+        collect_hops(child, hops);
+    }
+}
+
+/// Builds the radio network tree from the parent of every node, rooted at the gateway.
+pub fn build(nodes: &[Node]) -> Topology {
+    let mut children: HashMap<i32, Vec<&Node>> = HashMap::new();
+    for node in nodes.iter().filter(|node| node.node_id != GATEWAY_NODE_ID) {
+        children.entry(node.parent_node_id).or_default().push(node);
+    }
+    let gateway_name = nodes
+        .iter()
+        .find(|node| node.node_id == GATEWAY_NODE_ID)
+        .map(|node| node.node_name.clone())
+        .unwrap_or_else(|| "Gateway".to_owned());
+    let mut reached = HashSet::new();
+    let gateway = TopologyNode {
+        node_id: GATEWAY_NODE_ID,
+        node_name: gateway_name,
+        kind: NodeKind::Gateway,
+        parent_node_id: None,
+        hops: Some(0),
+        children: build_children(GATEWAY_NODE_ID, 1, &children, &mut reached),
+    };
+    let unreachable = nodes
+        .iter()
+        .filter(|node| node.node_id != GATEWAY_NODE_ID && !reached.contains(&node.node_id))
+        .map(|node| TopologyNode {
+            node_id: node.node_id,
+            node_name: node.node_name.clone(),
+            kind: kind(node),
+            parent_node_id: Some(node.parent_node_id),
+            hops: None,
+            children: Vec::new(),
+        })
+        .collect();
+    Topology {
+        gateway,
+        unreachable,
+    }
+}
+
+/// Node and edge document of the radio network, edges point from a node to its parent.
+pub fn graph(nodes: &[Node]) -> Graph {
+    let topology = build(nodes);
+    let hops = topology.hops();
+    let gateway_node = nodes.iter().find(|node| node.node_id == GATEWAY_NODE_ID);
+    let mut graph_nodes = vec![GraphNode {
+        id: GATEWAY_NODE_ID,
+        name: topology.gateway.node_name.clone(),
+        kind: NodeKind::Gateway,
+        hops: Some(0),
+        battery_level: None,
+        last_seen: gateway_node.and_then(|node| node.last_seen),
+    }];
+    let mut edges = Vec::new();
+    let mut known_node_ids: HashSet<i32> = nodes.iter().map(|node| node.node_id).collect();
+    known_node_ids.insert(GATEWAY_NODE_ID);
+    for node in nodes.iter().filter(|node| node.node_id != GATEWAY_NODE_ID) {
+        graph_nodes.push(GraphNode {
+            id: node.node_id,
+            name: node.node_name.clone(),
+            kind: kind(node),
+            hops: hops.get(&node.node_id).cloned(),
+            battery_level: node.battery_level,
+            last_seen: node.last_seen,
+        });
+        if known_node_ids.contains(&node.parent_node_id) {
+            edges.push(GraphEdge {
+                source: node.node_id,
+                target: node.parent_node_id,
+            });
+        }
+    }
+    Graph {
+        nodes: graph_nodes,
+        edges,
+    }
+}
+
+/// Renders the radio network as a Graphviz DOT digraph.
+pub fn to_dot(nodes: &[Node]) -> String {
+    let graph = graph(nodes);
+    let mut dot = String::from("digraph mysensors {\n");
+    for node in &graph.nodes {
+        let mut label = format!("{} ({})", escape_dot(&node.name), node.id);
+        if let Some(battery_level) = node.battery_level {
+            let _ = write!(label, "\\nbattery: {}%", battery_level);
+        }
+        if let Some(last_seen) = node.last_seen {
+            let _ = write!(label, "\\nlast seen: {}", last_seen.format("%Y-%m-%d %H:%M:%S"));
+        }
+        let shape = match node.kind {
+            NodeKind::Gateway => "box",
+            NodeKind::Repeater => "diamond",
+            NodeKind::Node => "ellipse",
+        };
+        let _ = writeln!(
+            dot,
+            "    \"{}\" [label=\"{}\", shape={}];",
+            node.id,
+            label,
+            shape
+        );
+    }
+    for edge in &graph.edges {
+        let _ = writeln!(dot, "    \"{}\" -> \"{}\";", edge.source, edge.target);
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Escapes text for a quoted DOT string, backslashes first so the added ones are kept.
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn build_children(
+    parent_node_id: i32,
+    hops: u32,
+    children: &HashMap<i32, Vec<&Node>>,
+    reached: &mut HashSet<i32>,
+) -> Vec<TopologyNode> {
+    let mut topology_nodes = Vec::new();
+    for node in children.get(&parent_node_id).into_iter().flatten() {
+        if !reached.insert(node.node_id) {
+            continue;
+        }
+        topology_nodes.push(TopologyNode {
+            node_id: node.node_id,
+            node_name: node.node_name.clone(),
+            kind: kind(node),
+            parent_node_id: Some(parent_node_id),
+            hops: Some(hops),
+            children: build_children(node.node_id, hops + 1, children, reached),
+        });
+    }
+    topology_nodes.sort_by_key(|node| node.node_id);
+    topology_nodes
+}
+
+fn kind(node: &Node) -> NodeKind {
+    if node.is_repeater {
+        NodeKind::Repeater
+    } else {
+        NodeKind::Node
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn node(node_id: i32, parent_node_id: i32, is_repeater: bool) -> Node {
+        Node {
+            node_id,
+            node_name: format!("Node {}", node_id),
+            parent_node_id,
+            is_repeater,
+            ..Node::default()
+        }
+    }
+
+    #[test]
+    fn builds_tree_with_hops() {
+        let topology = build(&[node(1, 0, true), node(2, 1, false), node(3, 0, false)]);
+        assert_eq!(Some(0), topology.gateway.hops);
+        let repeater = &topology.gateway.children[0];
+        assert_eq!((1, NodeKind::Repeater, Some(1)), (repeater.node_id, repeater.kind, repeater.hops));
+        assert_eq!((2, Some(2)), (repeater.children[0].node_id, repeater.children[0].hops));
+        assert_eq!(3, topology.gateway.children[1].node_id);
+        assert!(topology.unreachable.is_empty());
+    }
+
+    #[test]
+    fn nodes_not_reaching_gateway_are_unreachable() {
+        let topology = build(&[node(1, 9, false), node(2, 3, false), node(3, 2, false)]);
+        assert!(topology.gateway.children.is_empty());
+        let unreachable: Vec<i32> = topology.unreachable.iter().map(|node| node.node_id).collect();
+        assert_eq!(vec![1, 2, 3], unreachable);
+        assert_eq!(None, topology.unreachable[0].hops);
+    }
+
+    #[test]
+    fn gateway_name_comes_from_node_zero() {
+        let mut gateway = node(0, 0, false);
+        gateway.node_name = "Attic gateway".to_owned();
+        let topology = build(&[gateway, node(1, 0, false)]);
+        assert_eq!("Attic gateway", topology.gateway.node_name);
+        assert_eq!(1, topology.gateway.children.len());
+    }
+
+    #[test]
+    fn graph_has_edges_to_known_parents() {
+        let graph = graph(&[node(1, 0, true), node(2, 1, false), node(3, 9, false)]);
+        assert_eq!(4, graph.nodes.len());
+        assert_eq!(
+            vec![
+                GraphEdge { source: 1, target: 0 },
+                GraphEdge { source: 2, target: 1 },
+            ],
+            graph.edges
+        );
+        assert_eq!(Some(2), graph.nodes[2].hops);
+        assert_eq!(None, graph.nodes[3].hops);
+    }
+
+    #[test]
+    fn dot_export() {
+        let mut sensor_node = node(1, 0, false);
+        sensor_node.node_name = "Living \"room\" \\1".to_owned();
+        sensor_node.battery_level = Some(80);
+        sensor_node.last_seen = Some(NaiveDateTime::from_timestamp(0, 0));
+        assert_eq!(
+            "digraph mysensors {\n    \
+             \"0\" [label=\"Gateway (0)\", shape=box];\n    \
+             \"1\" [label=\"Living \\\"room\\\" \\\\1 (1)\\nbattery: 80%\\nlast seen: 1970-01-01 00:00:00\", shape=ellipse];\n    \
+             \"1\" -> \"0\";\n\
+             }\n",
+            to_dot(&[sensor_node])
+        );
+    }
+}